@@ -0,0 +1,78 @@
+//! Code-generates a typed NEAR view-call client from `contract_abi.json`, analogous to
+//! how Ethereum crates run `abigen!` in their build script. The generated client lands
+//! in `OUT_DIR/near_contract_client.rs` and is pulled in via `include!` from
+//! `src/policy.rs`.
+
+use serde::Deserialize;
+use std::env;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+#[derive(Deserialize)]
+struct Abi {
+    methods: Vec<AbiMethod>,
+}
+
+#[derive(Deserialize)]
+struct AbiMethod {
+    name: String,
+    args: Vec<AbiArg>,
+    return_ty: String,
+}
+
+#[derive(Deserialize)]
+struct AbiArg {
+    name: String,
+    ty: String,
+}
+
+fn pascal_case(s: &str) -> String {
+    s.split('_')
+        .map(|part| {
+            let mut c = part.chars();
+            match c.next() {
+                Some(first) => first.to_uppercase().collect::<String>() + c.as_str(),
+                None => String::new(),
+            }
+        })
+        .collect()
+}
+
+fn main() {
+    let manifest_dir = env::var("CARGO_MANIFEST_DIR").unwrap();
+    let abi_path = Path::new(&manifest_dir).join("contract_abi.json");
+    println!("cargo:rerun-if-changed={}", abi_path.display());
+
+    let abi_json = fs::read_to_string(&abi_path)
+        .unwrap_or_else(|e| panic!("Failed to read {}: {}", abi_path.display(), e));
+    let abi: Abi = serde_json::from_str(&abi_json)
+        .unwrap_or_else(|e| panic!("Invalid contract ABI JSON in {}: {}", abi_path.display(), e));
+
+    let mut out = String::new();
+    out.push_str("// @generated by build.rs from contract_abi.json. Do not edit by hand.\n\n");
+
+    for method in &abi.methods {
+        let struct_name = format!("{}Args", pascal_case(&method.name));
+        out.push_str("#[derive(Debug, Clone, serde::Serialize)]\n");
+        let _ = writeln!(out, "pub struct {} {{", struct_name);
+        for arg in &method.args {
+            let _ = writeln!(out, "    pub {}: {},", arg.name, arg.ty);
+        }
+        out.push_str("}\n\n");
+
+        let _ = writeln!(
+            out,
+            "impl NearContractClient {{\n    pub async fn {name}(&self, args: {struct_name}) -> anyhow::Result<{ret}> {{\n        self.view(\"{name}\", &args).await\n    }}\n}}\n",
+            name = method.name,
+            struct_name = struct_name,
+            ret = method.return_ty,
+        );
+    }
+
+    let out_dir = env::var("OUT_DIR").unwrap();
+    let dest_path = Path::new(&out_dir).join("near_contract_client.rs");
+    fs::write(&dest_path, out).unwrap_or_else(|e| {
+        panic!("Failed to write generated client to {}: {}", dest_path.display(), e)
+    });
+}
@@ -11,6 +11,9 @@ fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         kp_address: "localhost".to_string(),
         kp_port: 8080,
         vm_dir: "/tmp".to_string(),
+        policy_contract_id: "key-release-policy.near".to_string(),
+        policy_rpc_endpoint: "https://rpc.mainnet.near.org".to_string(),
+        admin_account_ids: vec!["admin.near".to_string()],
     };
 
     // Start the server in a thread
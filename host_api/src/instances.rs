@@ -0,0 +1,275 @@
+//! REST lifecycle API for instances managed by [`tdx_host_lib::DStackManager`]: create,
+//! start/stop, enumerate, and tail logs. Also exposes a best-effort exec channel for
+//! debugging, bridging a process's stdio over a WebSocket -- spawned inside the guest, not on
+//! the host, by dialing the instance's vsock CID and handing off to an in-guest exec agent.
+//!
+//! Note: the exec channel pipes stdio rather than allocating a real pseudo-terminal (the guest
+//! agent this bridges to has no pty dependency either), so resize control messages are accepted
+//! but ignored and programs that require a tty (e.g. ones that check `isatty`) will behave as if
+//! piped.
+
+use std::path::Path;
+use std::sync::Arc;
+
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{Path as AxumPath, Query, State};
+use axum::response::{IntoResponse, Response};
+use axum::{http::StatusCode, Json};
+use serde::{Deserialize, Serialize};
+use tdx_host_lib::InstanceRecord;
+use thiserror::Error;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio_vsock::{VsockAddr, VsockStream};
+
+use crate::AppState;
+
+/// Fixed vsock port the in-guest exec agent listens on. A connection opens with one
+/// newline-terminated JSON request, `{"cmd": ["program", "arg", ...]}`, after which the stream
+/// is raw, bidirectional process stdio (stdout+stderr interleaved on the read side, stdin on the
+/// write side) until the guest process exits and the agent closes the connection.
+const GUEST_EXEC_VSOCK_PORT: u32 = 10101;
+
+#[derive(Error, Debug)]
+pub enum InstanceError {
+    #[error("Instance not found: {0}")]
+    NotFound(String),
+
+    #[error("{0}")]
+    Internal(#[from] anyhow::Error),
+}
+
+impl IntoResponse for InstanceError {
+    fn into_response(self) -> Response {
+        let (status, message) = match &self {
+            InstanceError::NotFound(id) => (StatusCode::NOT_FOUND, format!("No such instance: {}", id)),
+            InstanceError::Internal(e) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Internal server error: {}", e),
+            ),
+        };
+        (status, Json(serde_json::json!({ "error": message }))).into_response()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateInstanceRequest {
+    pub compose_file: String,
+    pub image_path: String,
+    pub vcpus: u32,
+    pub memory: String,
+    pub disk: String,
+    #[serde(default)]
+    pub gpus: Vec<String>,
+    #[serde(default)]
+    pub ports: Vec<String>,
+    #[serde(default)]
+    pub local_key_provider: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CreateInstanceResponse {
+    pub id: String,
+}
+
+pub async fn create_instance(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<CreateInstanceRequest>,
+) -> Result<Json<CreateInstanceResponse>, InstanceError> {
+    let id = state
+        .manager
+        .create_instance(
+            Path::new(&req.compose_file),
+            Path::new(&req.image_path),
+            req.vcpus,
+            &req.memory,
+            &req.disk,
+            &req.gpus,
+            &req.ports,
+            req.local_key_provider,
+        )
+        .map_err(InstanceError::Internal)?;
+    Ok(Json(CreateInstanceResponse { id }))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StartInstanceRequest {
+    pub host_port: u16,
+}
+
+pub async fn start_instance(
+    State(state): State<Arc<AppState>>,
+    AxumPath(id): AxumPath<String>,
+    Json(req): Json<StartInstanceRequest>,
+) -> Result<StatusCode, InstanceError> {
+    require_instance(&state, &id)?;
+    state
+        .manager
+        .start_instance(&id, req.host_port)
+        .map_err(InstanceError::Internal)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn stop_instance(
+    State(state): State<Arc<AppState>>,
+    AxumPath(id): AxumPath<String>,
+) -> Result<StatusCode, InstanceError> {
+    require_instance(&state, &id)?;
+    state
+        .manager
+        .stop_instance(&id)
+        .map_err(InstanceError::Internal)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn list_instances(
+    State(state): State<Arc<AppState>>,
+) -> Json<Vec<InstanceRecord>> {
+    Json(state.manager.list_instances())
+}
+
+pub async fn get_instance(
+    State(state): State<Arc<AppState>>,
+    AxumPath(id): AxumPath<String>,
+) -> Result<Json<InstanceRecord>, InstanceError> {
+    Ok(Json(require_instance(&state, &id)?))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LogsQuery {
+    #[serde(default = "default_tail_lines")]
+    tail: usize,
+}
+
+fn default_tail_lines() -> usize {
+    200
+}
+
+pub async fn get_instance_logs(
+    State(state): State<Arc<AppState>>,
+    AxumPath(id): AxumPath<String>,
+    Query(query): Query<LogsQuery>,
+) -> Result<String, InstanceError> {
+    require_instance(&state, &id)?;
+    state
+        .manager
+        .instance_logs(&id, query.tail)
+        .map_err(InstanceError::Internal)
+}
+
+fn require_instance(state: &AppState, id: &str) -> Result<InstanceRecord, InstanceError> {
+    state
+        .manager
+        .get_instance(id)
+        .ok_or_else(|| InstanceError::NotFound(id.to_string()))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ExecQuery {
+    /// Command to run, e.g. `tail -f /var/log/app.log`. Split on whitespace; quoting is not
+    /// supported, so quote characters are passed through literally as part of an argument.
+    cmd: String,
+    #[serde(default)]
+    pty: bool,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ExecControlMessage {
+    Resize { cols: u16, rows: u16 },
+}
+
+pub async fn exec_instance(
+    State(state): State<Arc<AppState>>,
+    AxumPath(id): AxumPath<String>,
+    Query(query): Query<ExecQuery>,
+    ws: WebSocketUpgrade,
+) -> Result<Response, InstanceError> {
+    require_instance(&state, &id)?;
+    let cid = state
+        .manager
+        .instance_vsock_cid(&id)
+        .map_err(InstanceError::Internal)?;
+    Ok(ws.on_upgrade(move |socket| bridge_exec(socket, cid, query)))
+}
+
+/// Bridges a WebSocket to the in-guest exec agent at `cid`:[`GUEST_EXEC_VSOCK_PORT`], rather than
+/// spawning anything on the host.
+async fn bridge_exec(mut socket: WebSocket, cid: u32, query: ExecQuery) {
+    let cmd: Vec<&str> = query.cmd.split_whitespace().collect();
+    if cmd.is_empty() {
+        let _ = socket
+            .send(Message::Text("error: empty command\n".to_string()))
+            .await;
+        return;
+    }
+
+    if query.pty {
+        tracing::debug!("exec requested a pty; falling back to piped stdio (no pty backend)");
+    }
+
+    let mut guest = match VsockStream::connect(VsockAddr::new(cid, GUEST_EXEC_VSOCK_PORT)).await {
+        Ok(stream) => stream,
+        Err(e) => {
+            let _ = socket
+                .send(Message::Text(format!(
+                    "error: failed to reach guest exec agent at cid {}: {}\n",
+                    cid, e
+                )))
+                .await;
+            return;
+        }
+    };
+
+    let request = serde_json::json!({ "cmd": cmd });
+    let mut request = request.to_string();
+    request.push('\n');
+    if let Err(e) = guest.write_all(request.as_bytes()).await {
+        let _ = socket
+            .send(Message::Text(format!(
+                "error: failed to send exec request to guest: {}\n",
+                e
+            )))
+            .await;
+        return;
+    }
+
+    let (mut guest_read, mut guest_write) = tokio::io::split(guest);
+    let mut buf = [0u8; 4096];
+
+    loop {
+        tokio::select! {
+            result = guest_read.read(&mut buf) => {
+                match result {
+                    Ok(0) | Err(_) => break,
+                    Ok(n) => {
+                        if socket.send(Message::Binary(buf[..n].to_vec())).await.is_err() {
+                            break;
+                        }
+                    }
+                }
+            }
+            msg = socket.recv() => {
+                match msg {
+                    Some(Ok(Message::Text(text))) => {
+                        if let Ok(ExecControlMessage::Resize { cols, rows }) =
+                            serde_json::from_str::<ExecControlMessage>(&text)
+                        {
+                            tracing::debug!(cols, rows, "ignoring resize (no pty backend)");
+                            continue;
+                        }
+                        if !text.is_empty() && guest_write.write_all(text.as_bytes()).await.is_err() {
+                            break;
+                        }
+                    }
+                    Some(Ok(Message::Binary(bytes))) => {
+                        if guest_write.write_all(&bytes).await.is_err() {
+                            break;
+                        }
+                    }
+                    Some(Ok(Message::Close(_))) | None => break,
+                    _ => {}
+                }
+            }
+        }
+    }
+}
@@ -6,24 +6,64 @@ use std::sync::Arc;
 use std::thread;
 
 use axum::{
-    extract::State,
+    extract::{Request, State},
     http::StatusCode,
+    middleware::{self, Next},
     response::{IntoResponse, Response},
-    routing::post,
+    routing::{get, post},
     Json, Router,
 };
 use bytes::Bytes;
+use near_auth::{verify_signed_message, AuthData};
 use serde::{Deserialize, Serialize};
+use tdx_host_lib::DStackManager;
 use thiserror::Error;
 use tokio::net::TcpListener;
 use tower_http::trace::TraceLayer;
 
+mod instances;
+mod policy;
+mod quote;
+
+use policy::MeasurementPolicy;
+
 // Re-export the ServerConfig for external use
 #[derive(Clone, Debug)]
 pub struct ServerConfig {
     pub kp_address: String,
     pub kp_port: u16,
     pub vm_dir: String,
+    /// NEAR account id of the key-release-policy contract holding the measurement allowlist.
+    pub policy_contract_id: String,
+    /// NEAR RPC endpoint used for the `is_measurement_allowed` view call.
+    pub policy_rpc_endpoint: String,
+    /// NEAR account ids allowed to call the `/api/instances` management surface (create/start/
+    /// stop/exec/etc). Requests must present a `near_auth::AuthData` bearer token signed by one
+    /// of these accounts; see [`require_admin_auth`].
+    pub admin_account_ids: Vec<String>,
+}
+
+/// Shared server state: the static config, the (stateful, cached) policy client, and the
+/// instance lifecycle manager backing the `/api/instances` routes.
+struct AppState {
+    config: ServerConfig,
+    policy: MeasurementPolicy,
+    manager: DStackManager,
+}
+
+impl AppState {
+    fn new(config: ServerConfig) -> Self {
+        let policy = MeasurementPolicy::new(
+            config.policy_rpc_endpoint.clone(),
+            config.policy_contract_id.clone(),
+        );
+        let manager = DStackManager::with_run_path(Path::new(&config.vm_dir).to_path_buf());
+        Self {
+            config,
+            policy,
+            manager,
+        }
+    }
 }
 
 #[derive(Error, Debug)]
@@ -45,6 +85,12 @@ pub enum QuoteError {
 
     #[error("Data error: {0}")]
     Data(String),
+
+    #[error("Measurement not allowed by key-release policy")]
+    MeasurementNotAllowed,
+
+    #[error("Policy check failed: {0}")]
+    Policy(String),
 }
 
 impl IntoResponse for QuoteError {
@@ -55,6 +101,10 @@ impl IntoResponse for QuoteError {
                 "Request body too large".to_string(),
             ),
             QuoteError::NotFound => (StatusCode::NOT_FOUND, "Not found".to_string()),
+            QuoteError::MeasurementNotAllowed => (
+                StatusCode::FORBIDDEN,
+                "Measurement not allowed by key-release policy".to_string(),
+            ),
             err => (
                 StatusCode::INTERNAL_SERVER_ERROR,
                 format!("Internal server error: {}", err),
@@ -155,7 +205,7 @@ fn get_key(quote: Vec<u8>, address: &str, port: u16) -> Result<QuoteResponse, Qu
 }
 
 async fn get_sealing_key(
-    State(config): State<Arc<ServerConfig>>,
+    State(state): State<Arc<AppState>>,
     Json(payload): Json<QuoteRequest>,
 ) -> Result<Json<KeyResponse>, QuoteError> {
     // Check if the request is too large (already handled by axum's default limits)
@@ -163,7 +213,23 @@ async fn get_sealing_key(
     let quote = hex::decode(&payload.quote)
         .map_err(|_| QuoteError::Data("Invalid hex in quote".to_string()))?;
 
-    let response = get_key(quote, &config.kp_address, config.kp_port)?;
+    let measurement = quote::parse_td_measurement(&quote)
+        .map_err(|e| QuoteError::Data(format!("Failed to parse TDX quote: {}", e)))?;
+
+    let allowed = state
+        .policy
+        .is_allowed(&measurement)
+        .await
+        .map_err(|e| QuoteError::Policy(e.to_string()))?;
+    if !allowed {
+        tracing::warn!(
+            mrtd = measurement.mrtd_hex(),
+            "Rejecting GetSealingKey request: measurement not in on-chain allowlist"
+        );
+        return Err(QuoteError::MeasurementNotAllowed);
+    }
+
+    let response = get_key(quote, &state.config.kp_address, state.config.kp_port)?;
 
     Ok(Json(KeyResponse {
         encrypted_key: hex::encode(&response.encrypted_key),
@@ -172,13 +238,13 @@ async fn get_sealing_key(
 }
 
 async fn notify(
-    State(config): State<Arc<ServerConfig>>,
+    State(state): State<Arc<AppState>>,
     Json(payload): Json<NotifyRequest>,
 ) -> Result<Json<serde_json::Value>, QuoteError> {
     // Check if the request is too large (already handled by axum's default limits)
 
     if payload.event == "instance.info" {
-        let info_path = Path::new(&config.vm_dir)
+        let info_path = Path::new(&state.config.vm_dir)
             .join("shared")
             .join(".instance_info");
         let mut file = File::create(info_path)?;
@@ -188,18 +254,64 @@ async fn notify(
     Ok(Json(serde_json::json!(null)))
 }
 
+/// Authenticates and authorizes requests to the `/api/instances*` surface: create/start/stop
+/// instances and the exec channel can run arbitrary code, so unlike `/api/GetSealingKey` and
+/// `/api/Notify` (which are reached only by the enclave's own measured boot flow) they require a
+/// `near_auth::AuthData` bearer token signed by one of `config.admin_account_ids`.
+async fn require_admin_auth(
+    State(state): State<Arc<AppState>>,
+    auth: AuthData,
+    request: Request,
+    next: Next,
+) -> Result<Response, (StatusCode, String)> {
+    // Verify the signature before consulting the allowlist -- checking authorization first would
+    // let a caller submit an unsigned/forged `AuthData` and learn, from the 403 vs. 401, whether
+    // any given account_id is an admin without ever proving they control it.
+    if !verify_signed_message(&auth).await {
+        return Err((StatusCode::UNAUTHORIZED, "Invalid auth token".to_string()));
+    }
+    if !state.config.admin_account_ids.contains(&auth.account_id) {
+        return Err((
+            StatusCode::FORBIDDEN,
+            format!("Account {} is not an instances admin", auth.account_id),
+        ));
+    }
+    Ok(next.run(request).await)
+}
+
+/// Builds the full route table shared by [`start_server`] and [`start_server_in_thread`].
+fn build_router(state: Arc<AppState>) -> Router {
+    let instance_routes = Router::new()
+        .route(
+            "/api/instances",
+            get(instances::list_instances).post(instances::create_instance),
+        )
+        .route("/api/instances/{id}", get(instances::get_instance))
+        .route("/api/instances/{id}/start", post(instances::start_instance))
+        .route("/api/instances/{id}/stop", post(instances::stop_instance))
+        .route("/api/instances/{id}/logs", get(instances::get_instance_logs))
+        .route("/api/instances/{id}/exec", get(instances::exec_instance))
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            require_admin_auth,
+        ));
+
+    Router::new()
+        .route("/api/GetSealingKey", post(get_sealing_key))
+        .route("/api/Notify", post(notify))
+        .merge(instance_routes)
+        .with_state(state)
+        .layer(TraceLayer::new_for_http())
+}
+
 /// Starts the host API server with the given configuration
 ///
 /// This function can be called from another binary to start the server in a thread.
 pub async fn start_server(
-    config: Arc<ServerConfig>,
+    config: ServerConfig,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    // Build our application with routes
-    let app = Router::new()
-        .route("/api/GetSealingKey", post(get_sealing_key))
-        .route("/api/Notify", post(notify))
-        .with_state(config.clone())
-        .layer(TraceLayer::new_for_http());
+    let state = Arc::new(AppState::new(config));
+    let app = build_router(state);
 
     // Run the server
     let addr = SocketAddr::from(([127, 0, 0, 1], 0));
@@ -227,18 +339,12 @@ pub fn start_server_in_thread(
     let listener = rt.block_on(async { TcpListener::bind(addr).await })?;
     let server_addr = listener.local_addr()?;
 
-    // Clone the config for the thread
-    let config = Arc::new(config);
+    let state = Arc::new(AppState::new(config));
 
     // Spawn a thread to run the server
     let handle = thread::spawn(move || {
         rt.block_on(async {
-            // Build our application with routes
-            let app = Router::new()
-                .route("/api/GetSealingKey", post(get_sealing_key))
-                .route("/api/Notify", post(notify))
-                .with_state(config.clone())
-                .layer(TraceLayer::new_for_http());
+            let app = build_router(state);
 
             tracing::info!("Server listening on http://{}", server_addr);
 
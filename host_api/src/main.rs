@@ -1,5 +1,4 @@
 use host_api::{start_server, ServerConfig};
-use std::sync::Arc;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 #[tokio::main]
@@ -13,11 +12,20 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         .init();
 
     // Example configuration - replace with actual config loading logic
-    let config = Arc::new(ServerConfig {
+    let config = ServerConfig {
         kp_address: "localhost".to_string(),
         kp_port: 8080,
         vm_dir: "/tmp".to_string(),
-    });
+        policy_contract_id: "key-release-policy.near".to_string(),
+        policy_rpc_endpoint: "https://rpc.mainnet.near.org".to_string(),
+        admin_account_ids: std::env::var("INSTANCES_ADMIN_ACCOUNT_IDS")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(str::to_string)
+            .collect(),
+    };
 
     // Start the server
     start_server(config).await
@@ -0,0 +1,128 @@
+//! On-chain key-release policy: before the sealing key is relayed to the guest, the
+//! submitted quote's measurement registers are checked against an allowlist published
+//! in a NEAR smart contract.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use base64::Engine;
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::quote::TdMeasurement;
+
+/// How long a view-call result is trusted before we hit the RPC again.
+const CACHE_TTL: Duration = Duration::from_secs(30);
+
+/// Thin NEAR JSON-RPC client able to perform `call_function` view calls. The typed,
+/// per-method wrappers (e.g. `is_measurement_allowed`) are generated at build time from
+/// `contract_abi.json` into `OUT_DIR/near_contract_client.rs` and included below.
+pub struct NearContractClient {
+    http: reqwest::Client,
+    rpc_endpoint: String,
+    contract_account_id: String,
+}
+
+impl NearContractClient {
+    pub fn new(rpc_endpoint: String, contract_account_id: String) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            rpc_endpoint,
+            contract_account_id,
+        }
+    }
+
+    async fn view<A: Serialize, R: DeserializeOwned>(
+        &self,
+        method_name: &str,
+        args: &A,
+    ) -> anyhow::Result<R> {
+        let args_json = serde_json::to_vec(args)?;
+        let args_base64 = base64::engine::general_purpose::STANDARD.encode(args_json);
+
+        let body = serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": "host-api",
+            "method": "query",
+            "params": {
+                "request_type": "call_function",
+                "finality": "final",
+                "account_id": self.contract_account_id,
+                "method_name": method_name,
+                "args_base64": args_base64,
+            }
+        });
+
+        let resp = self
+            .http
+            .post(&self.rpc_endpoint)
+            .json(&body)
+            .send()
+            .await?
+            .json::<serde_json::Value>()
+            .await?;
+
+        let result_bytes = resp["result"]["result"]
+            .as_array()
+            .ok_or_else(|| anyhow::anyhow!("Unexpected RPC response shape: {:?}", resp))?
+            .iter()
+            .map(|v| v.as_u64().unwrap_or(0) as u8)
+            .collect::<Vec<u8>>();
+
+        let value: R = serde_json::from_slice(&result_bytes)?;
+        Ok(value)
+    }
+}
+
+include!(concat!(env!("OUT_DIR"), "/near_contract_client.rs"));
+
+/// Caches `is_measurement_allowed` results keyed by the measurement set, so the
+/// allowlist contract isn't queried on every sealing-key request.
+pub struct MeasurementPolicy {
+    client: NearContractClient,
+    cache: Mutex<HashMap<String, (bool, Instant)>>,
+}
+
+impl MeasurementPolicy {
+    pub fn new(rpc_endpoint: String, contract_account_id: String) -> Self {
+        Self {
+            client: NearContractClient::new(rpc_endpoint, contract_account_id),
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn cache_key(measurement: &TdMeasurement) -> String {
+        let mut key = measurement.mrtd_hex();
+        for rtmr in measurement.rtmrs_hex() {
+            key.push(':');
+            key.push_str(&rtmr);
+        }
+        key
+    }
+
+    /// Returns whether `measurement` is registered in the on-chain allowlist.
+    pub async fn is_allowed(&self, measurement: &TdMeasurement) -> anyhow::Result<bool> {
+        let key = Self::cache_key(measurement);
+
+        if let Some((allowed, checked_at)) = self.cache.lock().unwrap().get(&key).copied() {
+            if checked_at.elapsed() < CACHE_TTL {
+                return Ok(allowed);
+            }
+        }
+
+        let allowed = self
+            .client
+            .is_measurement_allowed(IsMeasurementAllowedArgs {
+                mrtd: measurement.mrtd_hex(),
+                rtmrs: measurement.rtmrs_hex(),
+            })
+            .await?;
+
+        self.cache
+            .lock()
+            .unwrap()
+            .insert(key, (allowed, Instant::now()));
+
+        Ok(allowed)
+    }
+}
@@ -0,0 +1,94 @@
+//! Parsing of the measurement registers out of a raw TDX quote.
+//!
+//! Layout follows the Intel TDX DCAP Quote v4 format: a 48-byte quote header
+//! followed by a 584-byte TD report body. We only need a handful of fixed-offset
+//! fields out of the body (MRTD, the four RTMRs, and the report data) to evaluate
+//! the on-chain key-release policy.
+
+use thiserror::Error;
+
+const HEADER_LEN: usize = 48;
+const MEASUREMENT_LEN: usize = 48;
+const REPORT_DATA_LEN: usize = 64;
+const MRTD_OFFSET: usize = HEADER_LEN + 136;
+const RTMR0_OFFSET: usize = HEADER_LEN + 328;
+const REPORT_DATA_OFFSET: usize = HEADER_LEN + 520;
+const MIN_QUOTE_LEN: usize = REPORT_DATA_OFFSET + REPORT_DATA_LEN;
+
+#[derive(Error, Debug)]
+pub enum QuoteParseError {
+    #[error("Quote too short: expected at least {MIN_QUOTE_LEN} bytes, got {0}")]
+    TooShort(usize),
+}
+
+/// The measurement registers extracted from a TD report.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TdMeasurement {
+    pub mrtd: [u8; MEASUREMENT_LEN],
+    pub rtmrs: [[u8; MEASUREMENT_LEN]; 4],
+    pub report_data: [u8; REPORT_DATA_LEN],
+}
+
+impl TdMeasurement {
+    pub fn mrtd_hex(&self) -> String {
+        hex::encode(self.mrtd)
+    }
+
+    pub fn rtmrs_hex(&self) -> Vec<String> {
+        self.rtmrs.iter().map(hex::encode).collect()
+    }
+}
+
+/// Extract MRTD, RTMR0-3, and report_data from a raw TDX quote.
+pub fn parse_td_measurement(quote: &[u8]) -> Result<TdMeasurement, QuoteParseError> {
+    if quote.len() < MIN_QUOTE_LEN {
+        return Err(QuoteParseError::TooShort(quote.len()));
+    }
+
+    let mut mrtd = [0u8; MEASUREMENT_LEN];
+    mrtd.copy_from_slice(&quote[MRTD_OFFSET..MRTD_OFFSET + MEASUREMENT_LEN]);
+
+    let mut rtmrs = [[0u8; MEASUREMENT_LEN]; 4];
+    for (i, rtmr) in rtmrs.iter_mut().enumerate() {
+        let offset = RTMR0_OFFSET + i * MEASUREMENT_LEN;
+        rtmr.copy_from_slice(&quote[offset..offset + MEASUREMENT_LEN]);
+    }
+
+    let mut report_data = [0u8; REPORT_DATA_LEN];
+    report_data.copy_from_slice(&quote[REPORT_DATA_OFFSET..REPORT_DATA_OFFSET + REPORT_DATA_LEN]);
+
+    Ok(TdMeasurement {
+        mrtd,
+        rtmrs,
+        report_data,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_td_measurement_too_short() {
+        let quote = vec![0u8; 10];
+        assert!(matches!(
+            parse_td_measurement(&quote),
+            Err(QuoteParseError::TooShort(10))
+        ));
+    }
+
+    #[test]
+    fn test_parse_td_measurement_extracts_fields() {
+        let mut quote = vec![0u8; MIN_QUOTE_LEN];
+        for (i, b) in quote[MRTD_OFFSET..MRTD_OFFSET + MEASUREMENT_LEN]
+            .iter_mut()
+            .enumerate()
+        {
+            *b = i as u8;
+        }
+        let measurement = parse_td_measurement(&quote).unwrap();
+        assert_eq!(measurement.mrtd[0], 0);
+        assert_eq!(measurement.mrtd[1], 1);
+        assert_eq!(measurement.rtmrs.len(), 4);
+    }
+}
@@ -0,0 +1,418 @@
+//! OCI image pulling and dm-verity rootfs measurement.
+//!
+//! Images are fetched by reference (`registry/repo:tag` or `registry/repo@sha256:...`),
+//! their layers are streamed into a content-addressed cache keyed by compressed digest,
+//! and the resulting unpacked rootfs is measured with a dm-verity Merkle tree so that
+//! `rootfs_hash` in `metadata.json` is reproducible from the image bytes themselves.
+
+use anyhow::{anyhow, Context, Result};
+use flate2::read::GzDecoder;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+const VERITY_BLOCK_SIZE: usize = 4096;
+
+/// Fixed domain-separation context for the dm-verity salt. The salt only needs to be stable and
+/// collision-resistant across measurements, not content-unique, so a constant keeps
+/// `measure_dm_verity`'s output a pure function of the image bytes -- deriving it from
+/// `image_path` instead (the assembled image's absolute path under the host's `run_path`) would
+/// make identical image content hash to a different `root_hash` on every host/deployment, which
+/// breaks both reproducibility and the on-chain measurement allowlist.
+const VERITY_SALT_CONTEXT: &[u8] = b"nearai-tdx-dm-verity-salt-v1";
+
+/// Descriptor written alongside `metadata.json` so the guest can reconstruct the
+/// dm-verity device without recomputing the Merkle tree from scratch.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VeritySuperblock {
+    pub root_hash: String,
+    pub salt: String,
+    pub block_size: usize,
+    pub data_block_count: u64,
+    pub hash_block_count: u64,
+}
+
+/// Persistent map of layer digest -> local blob path, so repeated pulls of shared
+/// base-image layers across instances are deduplicated.
+pub struct LayerCache {
+    dir: PathBuf,
+    index: HashMap<String, PathBuf>,
+}
+
+impl LayerCache {
+    pub fn open(dir: &Path) -> Result<Self> {
+        fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create image cache dir {}", dir.display()))?;
+        let index_path = dir.join("index.json");
+        let index = if index_path.exists() {
+            let data = fs::read_to_string(&index_path)
+                .with_context(|| format!("Failed to read {}", index_path.display()))?;
+            serde_json::from_str(&data).unwrap_or_default()
+        } else {
+            HashMap::new()
+        };
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            index,
+        })
+    }
+
+    fn persist(&self) -> Result<()> {
+        let index_path = self.dir.join("index.json");
+        let data = serde_json::to_vec_pretty(&self.index)?;
+        fs::write(&index_path, data)
+            .with_context(|| format!("Failed to write {}", index_path.display()))?;
+        Ok(())
+    }
+
+    /// Returns the local path for `digest`, fetching it via `fetch` (which should write
+    /// the raw layer bytes to the given path) only if it isn't already cached.
+    pub fn get_or_fetch(
+        &mut self,
+        digest: &str,
+        fetch: impl FnOnce(&Path) -> Result<()>,
+    ) -> Result<PathBuf> {
+        if let Some(path) = self.index.get(digest) {
+            if path.exists() {
+                return Ok(path.clone());
+            }
+        }
+
+        let blob_path = self.dir.join(sanitize_digest(digest));
+        fetch(&blob_path)
+            .with_context(|| format!("Failed to fetch layer {}", digest))?;
+
+        self.index.insert(digest.to_string(), blob_path.clone());
+        self.persist()?;
+        Ok(blob_path)
+    }
+}
+
+fn sanitize_digest(digest: &str) -> String {
+    digest.replace(':', "_")
+}
+
+/// A minimal OCI registry client: resolves a manifest and downloads layer blobs.
+/// Talks plain HTTPS to the registry's `/v2/` API (Docker Registry HTTP API v2).
+struct RegistryClient {
+    client: reqwest::blocking::Client,
+    registry: String,
+    repository: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct OciLayer {
+    digest: String,
+    #[serde(rename = "mediaType")]
+    media_type: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct OciManifest {
+    layers: Vec<OciLayer>,
+}
+
+impl RegistryClient {
+    fn new(registry: &str, repository: &str) -> Result<Self> {
+        Ok(Self {
+            client: reqwest::blocking::Client::builder()
+                .build()
+                .context("Failed to build registry HTTP client")?,
+            registry: registry.to_string(),
+            repository: repository.to_string(),
+        })
+    }
+
+    fn fetch_manifest(&self, reference: &str) -> Result<OciManifest> {
+        let url = format!(
+            "https://{}/v2/{}/manifests/{}",
+            self.registry, self.repository, reference
+        );
+        let resp = self
+            .client
+            .get(&url)
+            .header(
+                "Accept",
+                "application/vnd.oci.image.manifest.v1+json, application/vnd.docker.distribution.manifest.v2+json",
+            )
+            .send()
+            .with_context(|| format!("Failed to GET manifest from {}", url))?;
+        if !resp.status().is_success() {
+            return Err(anyhow!("Manifest fetch failed with status {}", resp.status()));
+        }
+        resp.json::<OciManifest>()
+            .context("Failed to parse OCI manifest")
+    }
+
+    fn download_layer(&self, digest: &str, dest: &Path) -> Result<()> {
+        let url = format!(
+            "https://{}/v2/{}/blobs/{}",
+            self.registry, self.repository, digest
+        );
+        let mut resp = self
+            .client
+            .get(&url)
+            .send()
+            .with_context(|| format!("Failed to GET layer blob from {}", url))?;
+        if !resp.status().is_success() {
+            return Err(anyhow!("Layer fetch failed with status {}", resp.status()));
+        }
+        let mut out = File::create(dest)
+            .with_context(|| format!("Failed to create {}", dest.display()))?;
+        resp.copy_to(&mut out)
+            .with_context(|| format!("Failed to stream layer to {}", dest.display()))?;
+        Ok(())
+    }
+}
+
+/// Parse `registry/repository:tag` or `registry/repository@digest` into parts.
+fn parse_reference(reference: &str) -> Result<(String, String, String)> {
+    let (path, tag) = if let Some(idx) = reference.rfind('@') {
+        (&reference[..idx], reference[idx + 1..].to_string())
+    } else if let Some(idx) = reference.rfind(':') {
+        // Guard against the ':' in a port number, e.g. "localhost:5000/repo".
+        if reference[idx + 1..].contains('/') {
+            (reference, "latest".to_string())
+        } else {
+            (&reference[..idx], reference[idx + 1..].to_string())
+        }
+    } else {
+        (reference, "latest".to_string())
+    };
+
+    let mut parts = path.splitn(2, '/');
+    let registry = parts
+        .next()
+        .ok_or_else(|| anyhow!("Invalid image reference: {}", reference))?
+        .to_string();
+    let repository = parts
+        .next()
+        .ok_or_else(|| anyhow!("Image reference missing repository: {}", reference))?
+        .to_string();
+
+    Ok((registry, repository, tag))
+}
+
+/// Pull `reference`, unpack its layers into `dest_dir/rootfs`, build a dm-verity Merkle
+/// tree over the assembled rootfs image, and write `metadata.json` + a verity superblock
+/// describing it.
+pub fn pull_and_measure(reference: &str, dest_dir: &Path, cache: &mut LayerCache) -> Result<()> {
+    let (registry, repository, tag) = parse_reference(reference)?;
+    let client = RegistryClient::new(&registry, &repository)?;
+
+    let manifest = client.fetch_manifest(&tag)?;
+
+    let rootfs_dir = dest_dir.join("rootfs");
+    fs::create_dir_all(&rootfs_dir)
+        .with_context(|| format!("Failed to create {}", rootfs_dir.display()))?;
+
+    for layer in &manifest.layers {
+        let registry = &registry;
+        let repository = &repository;
+        let digest = layer.digest.clone();
+        let blob_path = cache.get_or_fetch(&digest, |path| {
+            let client = RegistryClient::new(registry, repository)?;
+            client.download_layer(&digest, path)
+        })?;
+        unpack_layer(&blob_path, &layer.media_type, &rootfs_dir)
+            .with_context(|| format!("Failed to unpack layer {}", layer.digest))?;
+    }
+
+    let rootfs_image = assemble_rootfs_image(&rootfs_dir, dest_dir)?;
+    let superblock = measure_dm_verity(&rootfs_image)?;
+
+    let metadata_path = dest_dir.join("metadata.json");
+    let metadata = serde_json::json!({
+        "rootfs_hash": superblock.root_hash,
+        "rootfs": rootfs_image.file_name().and_then(|n| n.to_str()).unwrap_or("rootfs.img"),
+        "source_reference": reference,
+    });
+    let mut f = BufWriter::new(File::create(&metadata_path)?);
+    serde_json::to_writer_pretty(&mut f, &metadata)?;
+
+    let superblock_path = dest_dir.join("verity-superblock.json");
+    let mut sf = BufWriter::new(File::create(&superblock_path)?);
+    serde_json::to_writer_pretty(&mut sf, &superblock)?;
+
+    Ok(())
+}
+
+fn unpack_layer(blob_path: &Path, media_type: &str, dest_dir: &Path) -> Result<()> {
+    let file = File::open(blob_path)
+        .with_context(|| format!("Failed to open layer blob {}", blob_path.display()))?;
+
+    if media_type.contains("gzip") {
+        let decoder = GzDecoder::new(file);
+        let mut archive = tar::Archive::new(decoder);
+        archive
+            .unpack(dest_dir)
+            .with_context(|| format!("Failed to unpack gzip layer into {}", dest_dir.display()))?;
+    } else {
+        let mut archive = tar::Archive::new(file);
+        archive
+            .unpack(dest_dir)
+            .with_context(|| format!("Failed to unpack layer into {}", dest_dir.display()))?;
+    }
+    Ok(())
+}
+
+/// Concatenate the unpacked rootfs tree into a single flat image file suitable for
+/// dm-verity measurement (the guest mounts this as a read-only block device).
+fn assemble_rootfs_image(rootfs_dir: &Path, dest_dir: &Path) -> Result<PathBuf> {
+    let image_path = dest_dir.join("rootfs.img");
+    let mut out = BufWriter::new(File::create(&image_path)?);
+
+    let mut entries: Vec<PathBuf> = walk_files(rootfs_dir)?;
+    entries.sort();
+
+    for entry in entries {
+        let mut f = File::open(&entry)
+            .with_context(|| format!("Failed to open {}", entry.display()))?;
+        std::io::copy(&mut f, &mut out)
+            .with_context(|| format!("Failed to append {} to rootfs image", entry.display()))?;
+    }
+    out.flush()?;
+    Ok(image_path)
+}
+
+fn walk_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    if !dir.exists() {
+        return Ok(files);
+    }
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        if path.is_dir() {
+            files.extend(walk_files(&path)?);
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(files)
+}
+
+/// Build a dm-verity Merkle tree over `image_path`: split into 4096-byte blocks, hash
+/// each with SHA-256 to form the leaf level, then repeatedly hash concatenated child
+/// digests (zero-padding the last block of each level) until a single root remains.
+fn measure_dm_verity(image_path: &Path) -> Result<VeritySuperblock> {
+    let mut file = File::open(image_path)
+        .with_context(|| format!("Failed to open {}", image_path.display()))?;
+    let mut data = Vec::new();
+    file.read_to_end(&mut data)?;
+
+    let salt = {
+        let mut hasher = Sha256::new();
+        hasher.update(VERITY_SALT_CONTEXT);
+        hex::encode(hasher.finalize())
+    };
+    let salt_bytes = hex::decode(&salt).expect("salt is valid hex");
+
+    let data_block_count = data.len().div_ceil(VERITY_BLOCK_SIZE) as u64;
+
+    let mut level: Vec<[u8; 32]> = data
+        .chunks(VERITY_BLOCK_SIZE)
+        .map(|chunk| hash_block(chunk, &salt_bytes))
+        .collect();
+    if level.is_empty() {
+        level.push(hash_block(&[], &salt_bytes));
+    }
+
+    let mut hash_block_count: u64 = 0;
+    while level.len() > 1 {
+        let blocks_this_level = level.len().div_ceil(VERITY_BLOCK_SIZE / 32);
+        hash_block_count += blocks_this_level as u64;
+
+        let mut next_level = Vec::with_capacity(blocks_this_level);
+        for group in level.chunks(VERITY_BLOCK_SIZE / 32) {
+            let mut buf = vec![0u8; VERITY_BLOCK_SIZE];
+            for (i, digest) in group.iter().enumerate() {
+                buf[i * 32..(i + 1) * 32].copy_from_slice(digest);
+            }
+            next_level.push(hash_block(&buf, &salt_bytes));
+        }
+        level = next_level;
+    }
+
+    Ok(VeritySuperblock {
+        root_hash: hex::encode(level[0]),
+        salt,
+        block_size: VERITY_BLOCK_SIZE,
+        data_block_count,
+        hash_block_count,
+    })
+}
+
+fn hash_block(chunk: &[u8], salt: &[u8]) -> [u8; 32] {
+    let mut padded = [0u8; VERITY_BLOCK_SIZE];
+    padded[..chunk.len()].copy_from_slice(chunk);
+
+    let mut hasher = Sha256::new();
+    hasher.update(salt);
+    hasher.update(padded);
+    hasher.finalize().into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_reference_with_tag() {
+        let (registry, repo, tag) = parse_reference("registry.example.com/foo/bar:1.2").unwrap();
+        assert_eq!(registry, "registry.example.com");
+        assert_eq!(repo, "foo/bar");
+        assert_eq!(tag, "1.2");
+    }
+
+    #[test]
+    fn test_parse_reference_with_digest() {
+        let (registry, repo, tag) =
+            parse_reference("registry.example.com/foo/bar@sha256:abcd").unwrap();
+        assert_eq!(registry, "registry.example.com");
+        assert_eq!(repo, "foo/bar");
+        assert_eq!(tag, "sha256:abcd");
+    }
+
+    #[test]
+    fn test_parse_reference_defaults_to_latest() {
+        let (registry, repo, tag) = parse_reference("localhost:5000/foo").unwrap();
+        assert_eq!(registry, "localhost:5000");
+        assert_eq!(repo, "foo");
+        assert_eq!(tag, "latest");
+    }
+
+    #[test]
+    fn test_measure_dm_verity_is_deterministic() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("rootfs.img");
+        fs::write(&path, vec![7u8; VERITY_BLOCK_SIZE * 3 + 10]).unwrap();
+
+        let first = measure_dm_verity(&path).unwrap();
+        let second = measure_dm_verity(&path).unwrap();
+        assert_eq!(first.root_hash, second.root_hash);
+        assert_eq!(first.data_block_count, 4);
+    }
+
+    #[test]
+    fn test_measure_dm_verity_is_independent_of_image_path() {
+        let contents = vec![7u8; VERITY_BLOCK_SIZE * 3 + 10];
+
+        let dir_a = tempfile::tempdir().unwrap();
+        let path_a = dir_a.path().join("rootfs.img");
+        fs::write(&path_a, &contents).unwrap();
+
+        let dir_b = tempfile::tempdir().unwrap();
+        let path_b = dir_b.path().join("images").join("different-name.img");
+        fs::create_dir_all(path_b.parent().unwrap()).unwrap();
+        fs::write(&path_b, &contents).unwrap();
+
+        let from_a = measure_dm_verity(&path_a).unwrap();
+        let from_b = measure_dm_verity(&path_b).unwrap();
+        assert_eq!(from_a.root_hash, from_b.root_hash);
+        assert_eq!(from_a.salt, from_b.salt);
+    }
+}
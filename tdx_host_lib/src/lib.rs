@@ -0,0 +1,1226 @@
+use anyhow::{anyhow, Context, Result};
+use ini::configparser::ini::Ini;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Read};
+use std::num::ParseIntError;
+#[cfg(feature = "host")]
+use std::os::unix::io::RawFd;
+use std::path::{Path, PathBuf};
+#[cfg(feature = "host")]
+use std::process::{Command, Stdio};
+use std::sync::{Arc, Mutex};
+#[cfg(feature = "host")]
+use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing;
+use uuid::Uuid;
+
+pub mod image;
+#[cfg(feature = "host")]
+pub mod lua_builder;
+pub mod manager;
+pub mod nydus;
+#[cfg(feature = "host")]
+pub mod qmp;
+#[cfg(feature = "host")]
+pub mod vmm;
+
+#[cfg(feature = "host")]
+use qmp::QmpClient;
+
+pub use manager::{InstanceRecord, InstanceState};
+use manager::InstanceStore;
+
+/// Merge two JSON values in a nested/dict-like way, similar to Python's merge2.
+fn merge2(a: &Value, b: &Value) -> Value {
+    match (a, b) {
+        (Value::Object(a_map), Value::Object(b_map)) => {
+            let mut c = a_map.clone();
+            for (k, v) in b_map.iter() {
+                let merged_value = merge2(c.get(k).unwrap_or(&Value::Null), v);
+                c.insert(k.clone(), merged_value);
+            }
+            Value::Object(c)
+        }
+        (_, Value::Null) => a.clone(),
+        _ => b.clone(),
+    }
+}
+
+/// Load an INI file into a JSON object (section -> { key -> value }).
+fn ini_to_value(path: &Path) -> Result<Value> {
+    let mut ini = Ini::new();
+    let path_str = path.to_str().context("Path is not valid UTF-8")?;
+    ini.load(path_str)
+        .map_err(|e| anyhow!(e))
+        .context("Failed to load INI file")?;
+
+    let mut root = serde_json::Map::new();
+    if let Some(sections) = ini.get_map() {
+        for (section, properties) in sections.iter() {
+            let mut section_map = serde_json::Map::new();
+            for (key, value) in properties.iter() {
+                let value_str = match value {
+                    Some(v) => v.clone(),
+                    None => String::default(),
+                };
+                section_map.insert(key.to_string(), Value::String(value_str));
+            }
+            root.insert(section.to_string(), Value::Object(section_map));
+        }
+    }
+
+    Ok(Value::Object(root))
+}
+
+fn generate_config_paths() -> Vec<PathBuf> {
+    let mut paths = vec![
+        PathBuf::from("/etc/dstack/client.conf"),
+        dirs::home_dir()
+            .map(|mut p| {
+                p.push(".config/dstack/client.conf");
+                p
+            })
+            .unwrap_or_default(),
+    ];
+
+    let mut current_dir = std::env::current_dir().unwrap();
+    while current_dir != PathBuf::from("/") {
+        let mut conf_path = current_dir.clone();
+        conf_path.push(".dstack");
+        conf_path.push("client.conf");
+        paths.push(conf_path);
+        if !current_dir.pop() {
+            break;
+        }
+    }
+
+    paths
+}
+
+fn load_configs_merged() -> Value {
+    let mut merged = Value::Null;
+    for path in generate_config_paths() {
+        if path.exists() {
+            info!("Loading configuration from {}", path.display());
+            match ini_to_value(&path) {
+                Ok(v) => merged = merge2(&merged, &v),
+                Err(e) => warn!("Failed to parse '{}': {}", path.display(), e),
+            }
+        }
+    }
+    merged
+}
+
+/// The main user-facing config, analogous to the Python `DStackConfig`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DStackConfig {
+    pub docker_registry: Option<String>,
+    pub default_image_name: String,
+    pub qemu_path: String,
+    /// Directory used to cache pulled OCI image layers, keyed by digest.
+    pub image_cache_dir: PathBuf,
+    /// Optional Lua script whose `build_command(instance, vm)` runs after the default
+    /// argv builder to append QEMU arguments for nonstandard host setups. The script can only
+    /// append; arguments already produced by the default builder cannot be removed or replaced.
+    pub qemu_script: Option<PathBuf>,
+}
+
+impl Default for DStackConfig {
+    fn default() -> Self {
+        Self {
+            docker_registry: None,
+            default_image_name: "".to_string(),
+            qemu_path: "qemu-system-x86_64".to_string(),
+            image_cache_dir: PathBuf::from("/var/cache/dstack/images"),
+            qemu_script: None,
+        }
+    }
+}
+
+impl DStackConfig {
+    /// Loads and merges configuration from all known config paths.
+    pub fn load() -> Self {
+        let merged = load_configs_merged();
+
+        fn cfg_get(
+            root: &Value,
+            section: &str,
+            key: &str,
+            fallback: Option<String>,
+        ) -> Option<String> {
+            root.get(section)
+                .and_then(|sec_val| {
+                    if let Value::Object(obj) = sec_val {
+                        obj.get(key).and_then(|v| v.as_str().map(|s| s.to_string()))
+                    } else {
+                        None
+                    }
+                })
+                .or(fallback)
+        }
+
+        let mut me = DStackConfig::default();
+        let fallback_reg = me.docker_registry.clone();
+        me.docker_registry = cfg_get(&merged, "docker", "registry", fallback_reg);
+        me.default_image_name = cfg_get(&merged, "image", "default", Some(me.default_image_name))
+            .unwrap_or_else(|| "".to_string());
+        me.qemu_path = cfg_get(&merged, "qemu", "path", Some(me.qemu_path))
+            .unwrap_or_else(|| "qemu-system-x86_64".to_string());
+        me.image_cache_dir = cfg_get(
+            &merged,
+            "image",
+            "cache_dir",
+            Some(me.image_cache_dir.display().to_string()),
+        )
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("/var/cache/dstack/images"));
+        me.qemu_script = cfg_get(&merged, "qemu", "script", None).map(PathBuf::from);
+        me
+    }
+}
+
+/// PortMap, same as in Python.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PortMap {
+    pub address: String,
+    pub protocol: String,
+    #[serde(rename = "from")]
+    pub from_port: u16,
+    #[serde(rename = "to")]
+    pub to_port: u16,
+}
+
+/// VMConfig, same as in Python.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VMConfig {
+    pub id: String,
+    pub name: String,
+    pub vcpu: u32,
+    pub gpu: Vec<String>,
+    pub memory: u64,
+    pub disk_size: u64,
+    pub image: String,
+    pub image_path: String,
+    pub port_map: Vec<PortMap>,
+    pub created_at_ms: u64,
+    /// Which rootfs backend the guest should use: `"full"` (the image dir holds a
+    /// pre-built `rootfs.img`) or `"nydus"` (lazy chunk fetch against a blob store).
+    #[serde(default = "default_rootfs_backend")]
+    pub rootfs_backend: String,
+    /// Bootstrap + blob references, present only when `rootfs_backend == "nydus"`.
+    #[serde(default)]
+    pub nydus: Option<nydus::NydusConfig>,
+}
+
+/// Reconnection metadata persisted alongside a snapshot's `vmstate.dat` so
+/// [`DStackManager::restore`] can rebind networking and vsock without re-reading the
+/// original instance's `vm-manifest.json`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SnapshotMeta {
+    cid: u16,
+    host_port: u16,
+    image_path: String,
+    port_map: Vec<PortMap>,
+}
+
+fn default_rootfs_backend() -> String {
+    "full".to_string()
+}
+
+/// Convert a memory string (e.g. "1G", "512M", "2T") to MB.
+fn memory_to_mb(mem: &str) -> Result<u64> {
+    let upper = mem.trim().to_uppercase();
+    if upper.ends_with('T') {
+        let val: u64 = upper.trim_end_matches('T').parse()?;
+        Ok(val * 1024 * 1024)
+    } else if upper.ends_with('G') {
+        let val: u64 = upper.trim_end_matches('G').parse()?;
+        Ok(val * 1024)
+    } else if upper.ends_with('M') {
+        let val: u64 = upper.trim_end_matches('M').parse()?;
+        Ok(val)
+    } else {
+        Ok(upper.parse()?)
+    }
+}
+
+/// Parse a port mapping string "protocol[:address]:from:to".
+fn parse_port_mapping(port_str: &str) -> Result<PortMap> {
+    let parts: Vec<_> = port_str.split(':').collect();
+    match parts.len() {
+        3 => {
+            let proto = parts[0].to_lowercase();
+            let from_port = parts[1]
+                .parse::<u16>()
+                .map_err(|e: ParseIntError| anyhow!("Invalid from-port: {}", e))?;
+            let to_port = parts[2]
+                .parse::<u16>()
+                .map_err(|e: ParseIntError| anyhow!("Invalid to-port: {}", e))?;
+            Ok(PortMap {
+                address: "127.0.0.1".to_string(),
+                protocol: proto,
+                from_port,
+                to_port,
+            })
+        }
+        4 => {
+            let proto = parts[0].to_lowercase();
+            let address = parts[1].to_string();
+            let from_port = parts[2]
+                .parse::<u16>()
+                .map_err(|e: ParseIntError| anyhow!("Invalid from-port: {}", e))?;
+            let to_port = parts[3]
+                .parse::<u16>()
+                .map_err(|e: ParseIntError| anyhow!("Invalid to-port: {}", e))?;
+            Ok(PortMap {
+                address,
+                protocol: proto,
+                from_port,
+                to_port,
+            })
+        }
+        _ => Err(anyhow!(
+            "Invalid port mapping format. Use 'protocol[:address]:from:to' for '{}'",
+            port_str
+        )),
+    }
+}
+
+/// The main struct replicating the Python `DStackManager`. With the `client` feature alone
+/// (no `host`), only manifest/config generation (`setup_instance`, `create_instance`, and
+/// friends) is available; actually launching/controlling QEMU requires the `host` feature.
+pub struct DStackManager {
+    run_path: PathBuf,
+    pub config: DStackConfig,
+    #[cfg(feature = "host")]
+    qemu_processes: Arc<Mutex<Vec<std::process::Child>>>,
+    /// Per-instance children started through [`DStackManager::start_instance`], keyed by
+    /// instance id so they can be looked up again for `stop`/status/reaping.
+    #[cfg(feature = "host")]
+    instance_processes: Arc<Mutex<std::collections::HashMap<String, std::process::Child>>>,
+    instances: InstanceStore,
+}
+
+impl DStackManager {
+    /// Create a new manager, loading DStackConfig and setting up the default run_path.
+    pub fn new() -> Self {
+        let run_path = std::env::var("RUN_PATH")
+            .map(PathBuf::from)
+            .unwrap_or_else(|_| PathBuf::from("./vms"));
+        Self::with_run_path(run_path)
+    }
+
+    /// Like [`new`](Self::new), but with an explicit `run_path` instead of reading
+    /// `RUN_PATH`/defaulting to `./vms`. Useful for embedding a manager in another
+    /// process (e.g. `host_api`) that already knows its VM directory.
+    pub fn with_run_path(run_path: PathBuf) -> Self {
+        let run_path = run_path.canonicalize().unwrap_or(run_path);
+        let config = DStackConfig::load();
+        fs::create_dir_all(&run_path).ok();
+        let instances = InstanceStore::open(&run_path)
+            .unwrap_or_else(|e| panic!("Failed to open instance store at {}: {}", run_path.display(), e));
+
+        DStackManager {
+            run_path,
+            config,
+            #[cfg(feature = "host")]
+            qemu_processes: Arc::new(Mutex::new(Vec::new())),
+            #[cfg(feature = "host")]
+            instance_processes: Arc::new(Mutex::new(std::collections::HashMap::new())),
+            instances,
+        }
+    }
+
+    fn generate_instance_id(&self) -> String {
+        Uuid::new_v4().to_string()
+    }
+
+    fn create_directories(&self, work_dir: &Path) -> Result<(PathBuf, PathBuf)> {
+        if work_dir.exists() {
+            let entries = fs::read_dir(work_dir)?;
+            if entries.count() > 0 {
+                return Err(anyhow!(
+                    "Work directory {} is not empty",
+                    work_dir.display()
+                ));
+            }
+        }
+
+        let shared_dir = work_dir.join("shared");
+        let certs_dir = shared_dir.join("certs");
+        fs::create_dir_all(&shared_dir)?;
+        fs::create_dir_all(&certs_dir)?;
+        Ok((shared_dir, certs_dir))
+    }
+
+    fn read_compose_file(&self, compose_file: &Path) -> Result<String> {
+        if !compose_file.is_file() {
+            return Err(anyhow!(
+                "Compose file not found: {}",
+                compose_file.display()
+            ));
+        }
+        let mut f = File::open(compose_file)?;
+        let mut content = String::new();
+        f.read_to_string(&mut content)?;
+        Ok(content)
+    }
+
+    fn read_image_metadata(&self, image_path: &Path) -> Result<String> {
+        let metadata_path = image_path.join("metadata.json");
+        if !metadata_path.is_file() {
+            return Err(anyhow!(
+                "Image metadata not found at {}",
+                metadata_path.display()
+            ));
+        }
+        let file = File::open(&metadata_path)?;
+        let meta: Value = serde_json::from_reader(file).with_context(|| {
+            format!("Invalid JSON in metadata file {}", metadata_path.display())
+        })?;
+        let rootfs_hash = meta
+            .get("rootfs_hash")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow!("rootfs_hash not found in image info"))?;
+        Ok(rootfs_hash.to_string())
+    }
+
+    /// Resolves the rootfs backend for `image_path`: a nydus layout (bootstrap + blob
+    /// store) if present, otherwise the existing full-unpack `metadata.json` path.
+    /// Returns `(rootfs_hash, backend, nydus_config)`.
+    fn resolve_rootfs(
+        &self,
+        image_path: &Path,
+    ) -> Result<(String, String, Option<nydus::NydusConfig>)> {
+        if nydus::is_nydus_image(image_path) {
+            let (config, root_hash) = nydus::load_nydus_config(image_path)?;
+            Ok((root_hash, "nydus".to_string(), Some(config)))
+        } else {
+            let rootfs_hash = self.read_image_metadata(image_path)?;
+            Ok((rootfs_hash, "full".to_string(), None))
+        }
+    }
+
+    /// Pull an OCI image by reference, unpack it into a fresh image directory under
+    /// `run_path`, and measure its rootfs with dm-verity. Returns a path suitable for
+    /// passing straight into [`setup_instance`](Self::setup_instance) as `image_path`.
+    pub fn pull_image(&self, reference: &str) -> Result<PathBuf> {
+        let image_dir = self.run_path.join("images").join(sanitize_reference(reference));
+        fs::create_dir_all(&image_dir)
+            .with_context(|| format!("Failed to create image dir {}", image_dir.display()))?;
+
+        let mut cache = image::LayerCache::open(&self.config.image_cache_dir)?;
+        image::pull_and_measure(reference, &image_dir, &mut cache)
+            .with_context(|| format!("Failed to pull image '{}'", reference))?;
+
+        Ok(image_dir)
+    }
+
+    /// Equivalent to `setup_instance` in Python. Creates the instance directories,
+    /// writes out `app-compose.json`, `config.json`, and `vm-manifest.json`.
+    pub fn setup_instance(
+        &self,
+        compose_file: &Path,
+        work_dir_arg: Option<PathBuf>,
+        image_path: &Path,
+        vcpus: u32,
+        memory_str: &str,
+        disk_str: &str,
+        gpus: &[String],
+        ports: &[String],
+        local_key_provider: bool,
+    ) -> Result<()> {
+        let instance_id = match &work_dir_arg {
+            Some(dir) => dir
+                .file_name()
+                .unwrap_or_else(|| std::ffi::OsStr::new("unnamed"))
+                .to_string_lossy()
+                .to_string(),
+            None => self.generate_instance_id(),
+        };
+        let work_dir = work_dir_arg.unwrap_or_else(|| self.run_path.join(&instance_id));
+
+        let (shared_dir, _certs_dir) = self.create_directories(&work_dir)?;
+
+        let compose_content = self.read_compose_file(compose_file)?;
+
+        let app_compose = json!({
+            "manifest_version": 1,
+            "name": "example",
+            "version": "1.0.0",
+            "features": [],
+            "runner": "docker-compose",
+            "docker_compose_file": compose_content,
+            "local_key_provider_enabled": local_key_provider,
+        });
+        {
+            let path = shared_dir.join("app-compose.json");
+            let mut f = BufWriter::new(File::create(path)?);
+            serde_json::to_writer_pretty(&mut f, &app_compose)?;
+        }
+
+        let (rootfs_hash, rootfs_backend, nydus_config) = self.resolve_rootfs(image_path)?;
+
+        let config_obj = json!({
+            "rootfs_hash": rootfs_hash,
+            "docker_registry": self.config.docker_registry,
+            "pccs_url": "https://api.trustedservices.intel.com/sgx/certification/v4",
+        });
+        {
+            let path = shared_dir.join("config.json");
+            let mut cf = BufWriter::new(File::create(path)?);
+            serde_json::to_writer_pretty(&mut cf, &config_obj)?;
+        }
+
+        let memory_mb = memory_to_mb(memory_str)?;
+        let disk_mb = memory_to_mb(disk_str)? / 1024;
+        let mut port_map_vec = Vec::new();
+        for p in ports {
+            port_map_vec.push(parse_port_mapping(p)?);
+        }
+
+        let created_at_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as u64;
+
+        let image_name = image_path
+            .file_name()
+            .unwrap_or_else(|| std::ffi::OsStr::new("unknown"))
+            .to_string_lossy()
+            .to_string();
+
+        let vm_config = VMConfig {
+            id: instance_id.clone(),
+            name: "example".to_string(),
+            vcpu: vcpus,
+            gpu: gpus.to_vec(),
+            memory: memory_mb,
+            disk_size: disk_mb,
+            image_path: image_path.display().to_string(),
+            image: image_name,
+            port_map: port_map_vec,
+            created_at_ms,
+            rootfs_backend,
+            nydus: nydus_config,
+        };
+
+        {
+            let path = work_dir.join("vm-manifest.json");
+            let mut mf = BufWriter::new(File::create(path)?);
+            serde_json::to_writer_pretty(&mut mf, &vm_config)?;
+        }
+
+        info!(
+            "Work directory prepared successfully at: {}",
+            work_dir.display()
+        );
+        Ok(())
+    }
+
+    #[cfg(feature = "host")]
+    fn check_qemu_available(&self) -> Result<()> {
+        tracing::info!(
+            "Checking if QEMU is available at: {}",
+            self.config.qemu_path
+        );
+
+        let output = Command::new(&self.config.qemu_path)
+            .arg("--version")
+            .output()
+            .with_context(|| format!("Failed to execute QEMU at: {}", self.config.qemu_path))?;
+
+        if output.status.success() {
+            let version = String::from_utf8_lossy(&output.stdout);
+            tracing::info!("QEMU version: {}", version.trim());
+            Ok(())
+        } else {
+            let error = String::from_utf8_lossy(&output.stderr);
+            tracing::error!("QEMU check failed: {}", error);
+            Err(anyhow!("QEMU check failed: {}", error))
+        }
+    }
+
+    /// Spawns a QEMU process with TDX options for the instance at `vm_dir`.
+    #[cfg(feature = "host")]
+    pub fn run_instance(
+        &self,
+        vm_dir: &Path,
+        host_port: u16,
+        memory: Option<&str>,
+        vcpus: Option<u32>,
+        imgdir: Option<&Path>,
+    ) -> Result<()> {
+        let child = self.spawn_qemu_process(vm_dir, host_port, memory, vcpus, imgdir)?;
+        self.qemu_processes.lock().unwrap().push(child);
+        Ok(())
+    }
+
+    /// Builds the QEMU argv for the instance at `vm_dir` and spawns it, returning the
+    /// child handle without tracking it anywhere. Shared by [`run_instance`](Self::run_instance)
+    /// (which tracks children in an untagged list) and [`start_instance`](Self::start_instance)
+    /// (which tracks them per instance id in `instance_processes`).
+    #[cfg(feature = "host")]
+    fn spawn_qemu_process(
+        &self,
+        vm_dir: &Path,
+        host_port: u16,
+        memory: Option<&str>,
+        vcpus: Option<u32>,
+        imgdir: Option<&Path>,
+    ) -> Result<std::process::Child> {
+        self.spawn_qemu_process_ex(vm_dir, host_port, memory, vcpus, imgdir, None, None, &[])
+    }
+
+    /// Like [`spawn_qemu_process`](Self::spawn_qemu_process), with the extra knobs
+    /// [`restore`](Self::restore) needs: `cid_override` reuses a previously persisted vsock
+    /// CID instead of generating a new one, `incoming` boots QEMU into an incoming-migration
+    /// state (`-incoming exec:cat <path>`) instead of a fresh boot, and `net_fds` rebinds the
+    /// port-forwarding netdev to the first caller-supplied, already-connected socket fd (the
+    /// originals don't survive the snapshotted process exiting; any further fds are ignored)
+    /// instead of the manifest's user-mode `hostfwd` rules.
+    #[cfg(feature = "host")]
+    #[allow(clippy::too_many_arguments)]
+    fn spawn_qemu_process_ex(
+        &self,
+        vm_dir: &Path,
+        host_port: u16,
+        memory: Option<&str>,
+        vcpus: Option<u32>,
+        imgdir: Option<&Path>,
+        cid_override: Option<u16>,
+        incoming: Option<&Path>,
+        net_fds: &[RawFd],
+    ) -> Result<std::process::Child> {
+        self.check_qemu_available()?;
+
+        let manifest_path = vm_dir.join("vm-manifest.json");
+        if !manifest_path.exists() {
+            return Err(anyhow!(
+                "VM manifest not found in {}",
+                manifest_path.display()
+            ));
+        }
+        let mf = BufReader::new(File::open(&manifest_path)?);
+        let vm_config: VMConfig = serde_json::from_reader(mf)
+            .with_context(|| format!("Failed to parse {}", manifest_path.display()))?;
+
+        let image_path = if vm_config.image_path.is_empty() {
+            if let Some(idir) = imgdir {
+                idir.join(&vm_config.image)
+            } else {
+                return Err(anyhow!(
+                    "No image path in manifest and no `imgdir` provided"
+                ));
+            }
+        } else {
+            PathBuf::from(&vm_config.image_path)
+        };
+        let img_metadata_path = image_path.join("metadata.json");
+        if !img_metadata_path.exists() {
+            return Err(anyhow!(
+                "Image metadata not found: {}",
+                img_metadata_path.display()
+            ));
+        }
+
+        let shared_dir = vm_dir.join("shared");
+        let config_file = shared_dir.join("config.json");
+        if config_file.exists() {
+            let mut existing: Value = {
+                let cf = BufReader::new(File::open(&config_file)?);
+                serde_json::from_reader(cf)?
+            };
+            if let Value::Object(ref mut map) = existing {
+                map.insert(
+                    "host_api_url".to_string(),
+                    Value::String(format!("http://10.0.2.2:{}/api", host_port)),
+                );
+                map.insert(
+                    "host_vsock_port".to_string(),
+                    Value::Number(host_port.into()),
+                );
+            }
+            let mut wf = BufWriter::new(File::create(&config_file)?);
+            serde_json::to_writer_pretty(&mut wf, &existing)?;
+        }
+
+        let img_meta_f = BufReader::new(File::open(&img_metadata_path)?);
+        let img_metadata: Value = serde_json::from_reader(img_meta_f)
+            .with_context(|| format!("Invalid JSON in {}", img_metadata_path.display()))?;
+
+        let kernel = img_metadata
+            .get("kernel")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow!("Missing 'kernel' in metadata"))?;
+        let initrd = img_metadata
+            .get("initrd")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow!("Missing 'initrd' in metadata"))?;
+        let bios = img_metadata
+            .get("bios")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow!("Missing 'bios' in metadata"))?;
+        let rootfs = img_metadata
+            .get("rootfs")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow!("Missing 'rootfs' in metadata"))?;
+        let cmdline = img_metadata
+            .get("cmdline")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow!("Missing 'cmdline' in metadata"))?;
+
+        let mem_mb = if let Some(m_str) = memory {
+            memory_to_mb(m_str)?
+        } else {
+            vm_config.memory
+        };
+        let vcpus = vcpus.unwrap_or(vm_config.vcpu);
+        let disk_size = vm_config.disk_size;
+
+        let vda = vm_dir.join("hda.img");
+        if !vda.exists() {
+            let disk_arg = format!("{}G", disk_size);
+            Command::new("qemu-img")
+                .args(&["create", "-f", "qcow2"])
+                .arg(&vda)
+                .arg(&disk_arg)
+                .status()
+                .with_context(|| format!("Failed to create disk image at {}", vda.display()))?
+                .success()
+                .then_some(())
+                .ok_or_else(|| anyhow!("qemu-img create command failed"))?;
+        }
+
+        let cid: u16 = cid_override.unwrap_or_else(|| (rand::random::<u16>() % 10000) + 3);
+        fs::write(vm_dir.join("vsock_cid"), cid.to_string())
+            .with_context(|| format!("Failed to persist vsock CID for {}", vm_dir.display()))?;
+
+        let mut cmd = vec![
+            self.config.qemu_path.clone(),
+            "-accel".to_string(),
+            "kvm".to_string(),
+            "-m".to_string(),
+            format!("{}M", mem_mb),
+            "-smp".to_string(),
+            format!("{}", vcpus),
+            "-cpu".to_string(),
+            "host".to_string(),
+            "-machine".to_string(),
+            "q35,kernel_irqchip=split,confidential-guest-support=tdx,hpet=off".to_string(),
+            "-object".to_string(),
+            "tdx-guest,id=tdx".to_string(),
+            "-nographic".to_string(),
+            "-nodefaults".to_string(),
+            "-chardev".to_string(),
+            "null,id=ser0".to_string(),
+            "-serial".to_string(),
+            "chardev:ser0".to_string(),
+            "-kernel".to_string(),
+            image_path.join(kernel).display().to_string(),
+            "-initrd".to_string(),
+            image_path.join(initrd).display().to_string(),
+            "-bios".to_string(),
+            image_path.join(bios).display().to_string(),
+            "-cdrom".to_string(),
+            image_path.join(rootfs).display().to_string(),
+            "-drive".to_string(),
+            format!("file={},if=none,id=virtio-disk0", vda.display()),
+            "-device".to_string(),
+            "virtio-blk-pci,drive=virtio-disk0".to_string(),
+            "-virtfs".to_string(),
+            format!(
+                "local,path={},mount_tag=host-shared,readonly=off,security_model=mapped,id=virtfs0",
+                shared_dir.display()
+            ),
+            "-device".to_string(),
+            format!("vhost-vsock-pci,guest-cid={}", cid),
+            "-qmp".to_string(),
+            format!("unix:{},server,nowait", Self::qmp_socket_path(vm_dir).display()),
+        ];
+
+        cmd.push("-device".to_string());
+        cmd.push("virtio-net-pci,netdev=nic0_td".to_string());
+        cmd.push("-netdev".to_string());
+        if let Some(&fd) = net_fds.first() {
+            // Re-injected from the caller: the original tap/socket fd doesn't survive the
+            // snapshotted process exiting, so `restore` hands us a fresh, already-connected one.
+            cmd.push(format!("socket,id=nic0_td,fd={}", fd));
+        } else {
+            let mut port_forwards = Vec::new();
+            for pm in &vm_config.port_map {
+                port_forwards.push(format!(
+                    "hostfwd={}:{}:{}-:{}",
+                    pm.protocol, pm.address, pm.from_port, pm.to_port
+                ));
+            }
+            let mut netdev = String::from("user,id=nic0_td");
+            for pf in &port_forwards {
+                netdev.push(',');
+                netdev.push_str(pf);
+            }
+            cmd.push(netdev);
+        }
+
+        if let Some(vmstate_path) = incoming {
+            cmd.push("-incoming".to_string());
+            cmd.push(format!("exec:cat {}", vmstate_path.display()));
+        }
+
+        cmd.push("-append".to_string());
+        cmd.push(cmdline.to_string());
+
+        if let Some(script_path) = &self.config.qemu_script {
+            if script_path.is_file() {
+                cmd = lua_builder::apply_script(script_path, cmd, &vm_config).with_context(|| {
+                    format!("Failed to run qemu script {}", script_path.display())
+                })?;
+            }
+        }
+
+        tracing::info!("Launching QEMU with command:\n{}", cmd.join(" "));
+
+        let stdout_log = vm_dir.join("qemu_stdout.log");
+        let stderr_log = vm_dir.join("qemu_stderr.log");
+        let stdout_file = File::create(&stdout_log).with_context(|| {
+            format!("Failed to create stdout log file: {}", stdout_log.display())
+        })?;
+        let stderr_file = File::create(&stderr_log).with_context(|| {
+            format!("Failed to create stderr log file: {}", stderr_log.display())
+        })?;
+
+        let child = Command::new(&cmd[0])
+            .args(&cmd[1..])
+            .stdin(Stdio::null())
+            .stdout(Stdio::from(stdout_file))
+            .stderr(Stdio::from(stderr_file))
+            .spawn()
+            .with_context(|| format!("Failed to launch QEMU: {:?}", cmd))?;
+
+        tracing::info!("QEMU process started with PID: {}", child.id());
+
+        Ok(child)
+    }
+
+    /// Registers a new instance under `run_path` (via [`setup_instance`](Self::setup_instance))
+    /// and records it in the instance store with `Stopped` state. Returns the generated id.
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_instance(
+        &self,
+        compose_file: &Path,
+        image_path: &Path,
+        vcpus: u32,
+        memory_str: &str,
+        disk_str: &str,
+        gpus: &[String],
+        ports: &[String],
+        local_key_provider: bool,
+    ) -> Result<String> {
+        let instance_id = self.generate_instance_id();
+        self.setup_instance(
+            compose_file,
+            Some(self.run_path.join(&instance_id)),
+            image_path,
+            vcpus,
+            memory_str,
+            disk_str,
+            gpus,
+            ports,
+            local_key_provider,
+        )?;
+
+        let mut port_map = Vec::new();
+        for p in ports {
+            port_map.push(parse_port_mapping(p)?);
+        }
+        self.instances
+            .insert(InstanceRecord::new(instance_id.clone(), port_map))?;
+
+        Ok(instance_id)
+    }
+
+    /// Starts a previously-created instance, spawning QEMU and tracking the resulting
+    /// child process under the instance id so it can later be looked up by `stop`/`status`.
+    #[cfg(feature = "host")]
+    pub fn start_instance(&self, id: &str, host_port: u16) -> Result<()> {
+        let vm_dir = self.run_path.join(id);
+        let child = self.spawn_qemu_process(&vm_dir, host_port, None, None, None)?;
+        let pid = child.id();
+
+        self.instance_processes
+            .lock()
+            .unwrap()
+            .insert(id.to_string(), child);
+        self.instances.set_host_port(id, host_port)?;
+        self.instances
+            .update_state(id, InstanceState::Running, Some(pid))?;
+
+        Ok(())
+    }
+
+    /// Stops a running instance, sending it a kill signal and waiting for it to exit.
+    #[cfg(feature = "host")]
+    pub fn stop_instance(&self, id: &str) -> Result<()> {
+        let child = self.instance_processes.lock().unwrap().remove(id);
+        let Some(mut child) = child else {
+            return Err(anyhow!("Instance {} is not running", id));
+        };
+
+        child
+            .kill()
+            .with_context(|| format!("Failed to kill instance {}", id))?;
+        child
+            .wait()
+            .with_context(|| format!("Failed to wait on instance {}", id))?;
+
+        self.instances.update_state(id, InstanceState::Stopped, None)?;
+        Ok(())
+    }
+
+    /// Returns the persisted record for `id`, if any.
+    pub fn get_instance(&self, id: &str) -> Option<InstanceRecord> {
+        self.instances.get(id)
+    }
+
+    /// Returns every instance tracked under `run_path`.
+    pub fn list_instances(&self) -> Vec<InstanceRecord> {
+        self.instances.list()
+    }
+
+    /// Reads the tail of an instance's QEMU stdout/stderr logs.
+    pub fn instance_logs(&self, id: &str, tail_lines: usize) -> Result<String> {
+        let vm_dir = self.run_path.join(id);
+        let mut combined = String::new();
+        for log_name in ["qemu_stdout.log", "qemu_stderr.log"] {
+            let log_path = vm_dir.join(log_name);
+            if !log_path.is_file() {
+                continue;
+            }
+            let content = fs::read_to_string(&log_path)
+                .with_context(|| format!("Failed to read {}", log_path.display()))?;
+            let tail: Vec<&str> = content.lines().rev().take(tail_lines).collect();
+            for line in tail.into_iter().rev() {
+                combined.push_str(line);
+                combined.push('\n');
+            }
+        }
+        Ok(combined)
+    }
+
+    /// Returns the vsock CID assigned to `id`'s guest when it was last launched (persisted to
+    /// `vsock_cid` by [`start_instance`](Self::start_instance)),
+    /// for callers (e.g. the host API's exec channel) that need to dial into the VM over vsock
+    /// instead of going through the QMP control socket.
+    pub fn instance_vsock_cid(&self, id: &str) -> Result<u32> {
+        let vm_dir = self.run_path.join(id);
+        let cid_path = vm_dir.join("vsock_cid");
+        let cid: u16 = fs::read_to_string(&cid_path)
+            .with_context(|| format!("Failed to read {}", cid_path.display()))?
+            .trim()
+            .parse()
+            .with_context(|| format!("Invalid vsock CID in {}", cid_path.display()))?;
+        Ok(cid as u32)
+    }
+
+    /// Spawns a background thread that periodically polls every tracked instance process
+    /// and, when one has exited without us having called `stop_instance`, marks it
+    /// `Crashed` in the instance store so `list_instances`/`get_instance` reflect reality.
+    #[cfg(feature = "host")]
+    pub fn spawn_instance_watcher(self: Arc<Self>, poll_interval: std::time::Duration) -> thread::JoinHandle<()> {
+        thread::spawn(move || loop {
+            thread::sleep(poll_interval);
+
+            let exited: Vec<String> = {
+                let mut procs = self.instance_processes.lock().unwrap();
+                let mut exited = Vec::new();
+                procs.retain(|id, child| match child.try_wait() {
+                    Ok(Some(_status)) => {
+                        exited.push(id.clone());
+                        false
+                    }
+                    Ok(None) => true,
+                    Err(e) => {
+                        tracing::warn!("Failed to poll instance {}: {}", id, e);
+                        true
+                    }
+                });
+                exited
+            };
+
+            for id in exited {
+                tracing::warn!("Instance {} exited unexpectedly; marking crashed", id);
+                if let Err(e) = self.instances.update_state(&id, InstanceState::Crashed, None) {
+                    tracing::error!("Failed to persist crashed state for {}: {}", id, e);
+                }
+            }
+        })
+    }
+
+    /// Path to the QMP control socket for the instance at `vm_dir`.
+    #[cfg(feature = "host")]
+    fn qmp_socket_path(vm_dir: &Path) -> PathBuf {
+        vm_dir.join("qmp.sock")
+    }
+
+    #[cfg(feature = "host")]
+    fn connect_qmp(&self, vm_dir: &Path) -> Result<QmpClient> {
+        QmpClient::connect(&Self::qmp_socket_path(vm_dir))
+    }
+
+    /// Returns the guest's `query-status` result (e.g. `running`, `paused`, `shutdown`).
+    #[cfg(feature = "host")]
+    pub fn status(&self, vm_dir: &Path) -> Result<Value> {
+        self.connect_qmp(vm_dir)?.query_status()
+    }
+
+    /// Pauses (QMP `stop`) the guest at `vm_dir`.
+    #[cfg(feature = "host")]
+    pub fn pause(&self, vm_dir: &Path) -> Result<()> {
+        self.connect_qmp(vm_dir)?.stop()
+    }
+
+    /// Resumes (QMP `cont`) a previously paused guest at `vm_dir`.
+    #[cfg(feature = "host")]
+    pub fn resume(&self, vm_dir: &Path) -> Result<()> {
+        self.connect_qmp(vm_dir)?.cont()
+    }
+
+    /// Requests a graceful ACPI shutdown of the guest at `vm_dir`.
+    #[cfg(feature = "host")]
+    pub fn powerdown(&self, vm_dir: &Path) -> Result<()> {
+        self.connect_qmp(vm_dir)?.system_powerdown()
+    }
+
+    /// Returns the guest's PCI topology (`query-pci`), which includes the passed-through
+    /// GPU/VFIO devices.
+    #[cfg(feature = "host")]
+    pub fn query_gpu_topology(&self, vm_dir: &Path) -> Result<Value> {
+        self.connect_qmp(vm_dir)?.query_pci()
+    }
+
+    /// Pauses the guest at `vm_dir` and persists its VM state plus enough reconnection
+    /// metadata (vsock CID, host port, image path, port map) under `dest` for
+    /// [`restore`](Self::restore) to bring it back up without the original instance dir.
+    #[cfg(feature = "host")]
+    pub fn snapshot(&self, vm_dir: &Path, dest: &Path) -> Result<()> {
+        fs::create_dir_all(dest)
+            .with_context(|| format!("Failed to create snapshot dir {}", dest.display()))?;
+
+        self.pause(vm_dir)?;
+        self.connect_qmp(vm_dir)?
+            .migrate_to_file(&dest.join("vmstate.dat"))?;
+
+        let manifest_path = vm_dir.join("vm-manifest.json");
+        let mf = BufReader::new(File::open(&manifest_path)?);
+        let vm_config: VMConfig = serde_json::from_reader(mf)
+            .with_context(|| format!("Failed to parse {}", manifest_path.display()))?;
+
+        let cid: u16 = fs::read_to_string(vm_dir.join("vsock_cid"))
+            .ok()
+            .and_then(|s| s.trim().parse().ok())
+            .ok_or_else(|| anyhow!("No persisted vsock CID found for {}", vm_dir.display()))?;
+
+        let config_path = vm_dir.join("shared").join("config.json");
+        let config: Value = serde_json::from_reader(BufReader::new(File::open(&config_path)?))
+            .with_context(|| format!("Failed to parse {}", config_path.display()))?;
+        let host_port = config
+            .get("host_vsock_port")
+            .and_then(|v| v.as_u64())
+            .ok_or_else(|| anyhow!("No host_vsock_port recorded in {}", config_path.display()))?
+            as u16;
+
+        let snapshot_meta = SnapshotMeta {
+            cid,
+            host_port,
+            image_path: vm_config.image_path.clone(),
+            port_map: vm_config.port_map.clone(),
+        };
+        let mut sf = BufWriter::new(File::create(dest.join("snapshot-manifest.json"))?);
+        serde_json::to_writer_pretty(&mut sf, &snapshot_meta)?;
+
+        fs::copy(&manifest_path, dest.join("vm-manifest.json"))
+            .with_context(|| format!("Failed to copy manifest into snapshot dir {}", dest.display()))?;
+
+        Ok(())
+    }
+
+    /// Restores a guest previously [`snapshot`](Self::snapshot)ted to `src` into `vm_dir`,
+    /// re-injecting the first of `net_fds` as the port-forwarding netdev's backing socket (the original
+    /// host-side fds don't survive the snapshotted process exiting). The restored instance's
+    /// child is tracked the same way [`run_instance`](Self::run_instance) tracks a fresh boot.
+    #[cfg(feature = "host")]
+    pub fn restore(&self, vm_dir: &Path, src: &Path, net_fds: &[RawFd]) -> Result<()> {
+        let meta_path = src.join("snapshot-manifest.json");
+        let snapshot_meta: SnapshotMeta = serde_json::from_reader(BufReader::new(
+            File::open(&meta_path)
+                .with_context(|| format!("Snapshot manifest not found: {}", meta_path.display()))?,
+        ))
+        .with_context(|| format!("Failed to parse {}", meta_path.display()))?;
+
+        fs::copy(src.join("vm-manifest.json"), vm_dir.join("vm-manifest.json"))
+            .with_context(|| format!("Failed to restore manifest into {}", vm_dir.display()))?;
+
+        let child = self.spawn_qemu_process_ex(
+            vm_dir,
+            snapshot_meta.host_port,
+            None,
+            None,
+            None,
+            Some(snapshot_meta.cid),
+            Some(&src.join("vmstate.dat")),
+            net_fds,
+        )?;
+        self.qemu_processes.lock().unwrap().push(child);
+        Ok(())
+    }
+
+    /// Kills and reaps each QEMU process started by [`run_instance`](Self::run_instance) or
+    /// [`restore`](Self::restore). Instances started via [`start_instance`](Self::start_instance)
+    /// are not touched here.
+    #[cfg(feature = "host")]
+    pub fn shutdown_instances(&self) -> Result<()> {
+        let mut procs = self.qemu_processes.lock().unwrap();
+        tracing::info!("Shutting down {} QEMU instances", procs.len());
+
+        for child in procs.iter_mut() {
+            let pid = child.id();
+            tracing::info!("Shutting down QEMU instance (pid {})...", pid);
+
+            match child.kill() {
+                Ok(_) => tracing::info!("Sent kill signal to QEMU process {}", pid),
+                Err(e) => {
+                    tracing::warn!("Failed to send kill signal to QEMU process {}: {}", pid, e)
+                }
+            }
+
+            match child.wait() {
+                Ok(status) => {
+                    tracing::info!("QEMU process {} exited with status: {:?}", pid, status)
+                }
+                Err(e) => tracing::error!("Error waiting for QEMU process {}: {:?}", pid, e),
+            }
+        }
+
+        let count = procs.len();
+        procs.clear();
+        tracing::info!("Cleared {} QEMU processes from tracking list", count);
+
+        Ok(())
+    }
+
+    /// Copy a file to the VM's shared directory.
+    pub fn add_shared_file(&self, vm_dir: &Path, file_path: &str) -> Result<()> {
+        let src_path = Path::new(file_path);
+        let dest_path = vm_dir.join("shared").join(file_path);
+
+        if let Some(parent) = dest_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        fs::copy(&src_path, &dest_path).with_context(|| {
+            format!(
+                "Failed to copy {} to {}",
+                src_path.display(),
+                dest_path.display()
+            )
+        })?;
+
+        Ok(())
+    }
+}
+
+/// Turn an OCI reference into a filesystem-safe directory name.
+fn sanitize_reference(reference: &str) -> String {
+    reference
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '.' { c } else { '_' })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge2() {
+        let a = json!({"a":1});
+        let b = json!({"b":2});
+        let merged = merge2(&a, &b);
+        assert_eq!(merged, json!({"a":1,"b":2}));
+
+        let a = json!({"a":{"b":1}});
+        let b = json!({"a":{"c":2}});
+        let merged = merge2(&a, &b);
+        assert_eq!(merged, json!({"a":{"b":1,"c":2}}));
+    }
+
+    #[test]
+    fn test_memory_to_mb() {
+        assert_eq!(memory_to_mb("512").unwrap(), 512);
+        assert_eq!(memory_to_mb("1G").unwrap(), 1024);
+        assert_eq!(memory_to_mb("2G").unwrap(), 2048);
+        assert_eq!(memory_to_mb("2T").unwrap(), 2 * 1024 * 1024);
+    }
+
+    #[test]
+    fn test_parse_port_mapping() {
+        let pm1 = parse_port_mapping("tcp:8080:80").unwrap();
+        assert_eq!(pm1.protocol, "tcp");
+        assert_eq!(pm1.address, "127.0.0.1");
+        assert_eq!(pm1.from_port, 8080);
+        assert_eq!(pm1.to_port, 80);
+
+        let pm2 = parse_port_mapping("udp:0.0.0.0:53:53").unwrap();
+        assert_eq!(pm2.protocol, "udp");
+        assert_eq!(pm2.address, "0.0.0.0");
+        assert_eq!(pm2.from_port, 53);
+        assert_eq!(pm2.to_port, 53);
+
+        assert!(parse_port_mapping("tcp:8080").is_err());
+        assert!(parse_port_mapping("notvalid").is_err());
+    }
+
+    #[test]
+    fn test_sanitize_reference() {
+        assert_eq!(
+            sanitize_reference("registry.example.com/foo/bar:1.0"),
+            "registry.example.com_foo_bar_1.0"
+        );
+    }
+
+    #[test]
+    fn test_resolve_rootfs_picks_nydus_when_image_has_bootstrap_and_blobs() {
+        let manager = DStackManager::with_run_path(tempfile::tempdir().unwrap().path().to_path_buf());
+        let image_dir = tempfile::tempdir().unwrap();
+        fs::write(image_dir.path().join(nydus::BOOTSTRAP_FILE_NAME), b"boot").unwrap();
+        fs::create_dir(image_dir.path().join(nydus::BLOBS_DIR_NAME)).unwrap();
+        fs::write(image_dir.path().join(nydus::BLOBS_DIR_NAME).join("sha256_a"), b"a").unwrap();
+
+        let (rootfs_hash, backend, nydus_config) =
+            manager.resolve_rootfs(image_dir.path()).unwrap();
+        assert_eq!(backend, "nydus");
+        assert!(!rootfs_hash.is_empty());
+        assert_eq!(nydus_config.unwrap().blob_digests, vec!["sha256_a"]);
+    }
+
+    #[test]
+    fn test_resolve_rootfs_falls_back_to_full_unpack_metadata() {
+        let manager = DStackManager::with_run_path(tempfile::tempdir().unwrap().path().to_path_buf());
+        let image_dir = tempfile::tempdir().unwrap();
+        fs::write(
+            image_dir.path().join("metadata.json"),
+            json!({"rootfs_hash": "deadbeef"}).to_string(),
+        )
+        .unwrap();
+
+        let (rootfs_hash, backend, nydus_config) =
+            manager.resolve_rootfs(image_dir.path()).unwrap();
+        assert_eq!(backend, "full");
+        assert_eq!(rootfs_hash, "deadbeef");
+        assert!(nydus_config.is_none());
+    }
+}
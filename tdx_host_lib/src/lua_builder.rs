@@ -0,0 +1,143 @@
+//! Optional Lua-scripted QEMU argv customization, for host setups the hardcoded argv in
+//! `spawn_qemu_process` can't anticipate (nonstandard audio/display, extra `-device`
+//! lines, custom CPU flags). If `DStackConfig::qemu_script` points at a file, its
+//! `build_command(instance, vm)` function runs after the default builder and can append
+//! further tokens via `instance:arg(...)`.
+
+use crate::{PortMap, VMConfig};
+use anyhow::{Context, Result};
+use mlua::{Lua, UserData, UserDataMethods};
+use std::fs;
+use std::path::Path;
+
+/// Exposed to Lua as `instance`: a mutable handle onto the argv the default builder has
+/// already produced.
+pub struct VmBuilder {
+    pub args: Vec<String>,
+}
+
+impl UserData for VmBuilder {
+    fn add_methods<M: UserDataMethods<Self>>(methods: &mut M) {
+        methods.add_method_mut("arg", |_, this, token: String| {
+            this.args.push(token);
+            Ok(())
+        });
+    }
+}
+
+fn port_map_table(lua: &Lua, port_map: &[PortMap]) -> mlua::Result<mlua::Table> {
+    let table = lua.create_table()?;
+    for (i, pm) in port_map.iter().enumerate() {
+        let entry = lua.create_table()?;
+        entry.set("protocol", pm.protocol.clone())?;
+        entry.set("address", pm.address.clone())?;
+        entry.set("from", pm.from_port)?;
+        entry.set("to", pm.to_port)?;
+        table.set(i + 1, entry)?;
+    }
+    Ok(table)
+}
+
+/// Runs `script_path`'s `build_command(instance, vm)` against the argv the default
+/// builder produced (`args`) and the resolved `vm_config`, returning the final argv.
+pub fn apply_script(script_path: &Path, args: Vec<String>, vm_config: &VMConfig) -> Result<Vec<String>> {
+    let lua = Lua::new();
+    let script = fs::read_to_string(script_path)
+        .with_context(|| format!("Failed to read qemu script {}", script_path.display()))?;
+    lua.load(&script)
+        .exec()
+        .with_context(|| format!("Failed to load qemu script {}", script_path.display()))?;
+
+    let vm_table = lua
+        .create_table()
+        .context("Failed to build Lua vm table")?;
+    vm_table.set("vcpu", vm_config.vcpu)?;
+    vm_table.set("memory", vm_config.memory)?;
+    vm_table.set("gpu", vm_config.gpu.clone())?;
+    vm_table.set("port_map", port_map_table(&lua, &vm_config.port_map)?)?;
+
+    let builder_ud = lua
+        .create_userdata(VmBuilder { args })
+        .context("Failed to wrap VmBuilder for Lua")?;
+
+    let build_command: mlua::Function = lua
+        .globals()
+        .get("build_command")
+        .context("qemu.script must define a global build_command(instance, vm) function")?;
+    build_command
+        .call::<_, ()>((builder_ud.clone(), vm_table))
+        .context("qemu.script's build_command failed")?;
+
+    let builder = builder_ud
+        .borrow::<VmBuilder>()
+        .context("Failed to read back VmBuilder after running qemu.script")?;
+    Ok(builder.args.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_vm_config() -> VMConfig {
+        VMConfig {
+            id: "test".to_string(),
+            name: "test".to_string(),
+            vcpu: 4,
+            gpu: vec![],
+            memory: 2048,
+            disk_size: 20,
+            image: "img".to_string(),
+            image_path: "/tmp/img".to_string(),
+            port_map: vec![],
+            created_at_ms: 0,
+            rootfs_backend: "full".to_string(),
+            nydus: None,
+        }
+    }
+
+    #[test]
+    fn test_apply_script_appends_args() {
+        let dir = tempfile::tempdir().unwrap();
+        let script_path = dir.path().join("qemu.lua");
+        fs::write(
+            &script_path,
+            r#"
+            function build_command(instance, vm)
+                instance:arg("-device")
+                instance:arg("extra-device,id=custom0")
+            end
+            "#,
+        )
+        .unwrap();
+
+        let args = apply_script(&script_path, vec!["qemu-system-x86_64".to_string()], &sample_vm_config())
+            .unwrap();
+        assert_eq!(
+            args,
+            vec![
+                "qemu-system-x86_64".to_string(),
+                "-device".to_string(),
+                "extra-device,id=custom0".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_apply_script_can_read_vm_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let script_path = dir.path().join("qemu.lua");
+        fs::write(
+            &script_path,
+            r#"
+            function build_command(instance, vm)
+                instance:arg("-comment")
+                instance:arg("vcpu=" .. vm.vcpu)
+            end
+            "#,
+        )
+        .unwrap();
+
+        let args = apply_script(&script_path, vec![], &sample_vm_config()).unwrap();
+        assert_eq!(args, vec!["-comment".to_string(), "vcpu=4".to_string()]);
+    }
+}
@@ -0,0 +1,151 @@
+//! Tracks the lifecycle of every instance under `run_path`: state (stopped/running/
+//! crashed), pid, and port map, persisted to `instances.json` so `DStackManager::new()`
+//! picks up pre-existing instances after a restart.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::PortMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InstanceState {
+    Stopped,
+    Running,
+    Crashed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstanceRecord {
+    pub id: String,
+    pub state: InstanceState,
+    pub pid: Option<u32>,
+    pub host_port: Option<u16>,
+    pub port_map: Vec<PortMap>,
+    pub created_at_ms: u64,
+}
+
+impl InstanceRecord {
+    pub fn new(id: String, port_map: Vec<PortMap>) -> Self {
+        Self {
+            id,
+            state: InstanceState::Stopped,
+            pid: None,
+            host_port: None,
+            port_map,
+            created_at_ms: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_millis() as u64,
+        }
+    }
+}
+
+/// Persisted table of instance records, keyed by instance id.
+pub struct InstanceStore {
+    path: PathBuf,
+    records: Mutex<HashMap<String, InstanceRecord>>,
+}
+
+impl InstanceStore {
+    pub fn open(run_path: &Path) -> Result<Self> {
+        let path = run_path.join("instances.json");
+        let records = if path.is_file() {
+            let f = BufReader::new(File::open(&path)?);
+            serde_json::from_reader(f)
+                .with_context(|| format!("Failed to parse {}", path.display()))?
+        } else {
+            HashMap::new()
+        };
+        Ok(Self {
+            path,
+            records: Mutex::new(records),
+        })
+    }
+
+    fn persist(&self, records: &HashMap<String, InstanceRecord>) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let f = BufWriter::new(File::create(&self.path)?);
+        serde_json::to_writer_pretty(f, records)?;
+        Ok(())
+    }
+
+    pub fn insert(&self, record: InstanceRecord) -> Result<()> {
+        let mut records = self.records.lock().unwrap();
+        records.insert(record.id.clone(), record);
+        self.persist(&records)
+    }
+
+    pub fn update_state(&self, id: &str, state: InstanceState, pid: Option<u32>) -> Result<()> {
+        let mut records = self.records.lock().unwrap();
+        if let Some(record) = records.get_mut(id) {
+            record.state = state;
+            record.pid = pid;
+        }
+        self.persist(&records)
+    }
+
+    pub fn set_host_port(&self, id: &str, host_port: u16) -> Result<()> {
+        let mut records = self.records.lock().unwrap();
+        if let Some(record) = records.get_mut(id) {
+            record.host_port = Some(host_port);
+        }
+        self.persist(&records)
+    }
+
+    pub fn get(&self, id: &str) -> Option<InstanceRecord> {
+        self.records.lock().unwrap().get(id).cloned()
+    }
+
+    pub fn list(&self) -> Vec<InstanceRecord> {
+        self.records.lock().unwrap().values().cloned().collect()
+    }
+
+    pub fn remove(&self, id: &str) -> Result<()> {
+        let mut records = self.records.lock().unwrap();
+        records.remove(id);
+        self.persist(&records)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_insert_and_list_round_trips_through_disk() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = InstanceStore::open(dir.path()).unwrap();
+        store
+            .insert(InstanceRecord::new("abc".to_string(), vec![]))
+            .unwrap();
+
+        let reopened = InstanceStore::open(dir.path()).unwrap();
+        assert_eq!(reopened.list().len(), 1);
+        assert_eq!(reopened.get("abc").unwrap().state, InstanceState::Stopped);
+    }
+
+    #[test]
+    fn test_update_state_changes_pid_and_state() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = InstanceStore::open(dir.path()).unwrap();
+        store
+            .insert(InstanceRecord::new("abc".to_string(), vec![]))
+            .unwrap();
+        store
+            .update_state("abc", InstanceState::Running, Some(1234))
+            .unwrap();
+
+        let record = store.get("abc").unwrap();
+        assert_eq!(record.state, InstanceState::Running);
+        assert_eq!(record.pid, Some(1234));
+    }
+}
@@ -0,0 +1,93 @@
+//! Nydus-style lazy-loading rootfs backend: instead of unpacking every layer up front
+//! like [`crate::image`], a nydus image ships a small bootstrap (metadata) blob
+//! describing a chunk layout, and the guest fetches file chunks on demand from a
+//! content-addressed blob store instead of materializing the whole rootfs.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::Path;
+
+/// Layout of a nydus-format image directory: `image.boot` is the bootstrap blob, and
+/// `blobs/<digest>` holds the content-addressed data blobs it references.
+pub const BOOTSTRAP_FILE_NAME: &str = "image.boot";
+pub const BLOBS_DIR_NAME: &str = "blobs";
+
+/// References written into `vm-manifest.json` so the guest knows where to mount the
+/// bootstrap and how to resolve chunk fetches against the blob store.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NydusConfig {
+    pub bootstrap: String,
+    pub blob_digests: Vec<String>,
+}
+
+/// True if `image_path` looks like a nydus image (bootstrap + blob store) rather than a
+/// fully-unpacked rootfs image.
+pub fn is_nydus_image(image_path: &Path) -> bool {
+    image_path.join(BOOTSTRAP_FILE_NAME).is_file() && image_path.join(BLOBS_DIR_NAME).is_dir()
+}
+
+/// Build the manifest-facing config plus the `rootfs_hash` integrity root for a nydus
+/// image. The bootstrap blob already commits to every chunk digest it references, so
+/// hashing the bootstrap bytes themselves is enough to cover the whole image.
+pub fn load_nydus_config(image_path: &Path) -> Result<(NydusConfig, String)> {
+    let bootstrap_path = image_path.join(BOOTSTRAP_FILE_NAME);
+    let bootstrap_bytes = fs::read(&bootstrap_path)
+        .with_context(|| format!("Failed to read {}", bootstrap_path.display()))?;
+
+    let mut hasher = Sha256::new();
+    hasher.update(&bootstrap_bytes);
+    let root_hash = hex::encode(hasher.finalize());
+
+    let blobs_dir = image_path.join(BLOBS_DIR_NAME);
+    let mut blob_digests = Vec::new();
+    for entry in
+        fs::read_dir(&blobs_dir).with_context(|| format!("Failed to read {}", blobs_dir.display()))?
+    {
+        let entry = entry?;
+        if entry.path().is_file() {
+            blob_digests.push(entry.file_name().to_string_lossy().to_string());
+        }
+    }
+    blob_digests.sort();
+
+    Ok((
+        NydusConfig {
+            bootstrap: BOOTSTRAP_FILE_NAME.to_string(),
+            blob_digests,
+        },
+        root_hash,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_nydus_image_requires_both_bootstrap_and_blobs() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(!is_nydus_image(dir.path()));
+
+        fs::write(dir.path().join(BOOTSTRAP_FILE_NAME), b"boot").unwrap();
+        assert!(!is_nydus_image(dir.path()));
+
+        fs::create_dir(dir.path().join(BLOBS_DIR_NAME)).unwrap();
+        assert!(is_nydus_image(dir.path()));
+    }
+
+    #[test]
+    fn test_load_nydus_config_lists_sorted_blob_digests() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join(BOOTSTRAP_FILE_NAME), b"boot-metadata").unwrap();
+        let blobs_dir = dir.path().join(BLOBS_DIR_NAME);
+        fs::create_dir(&blobs_dir).unwrap();
+        fs::write(blobs_dir.join("sha256_b"), b"b").unwrap();
+        fs::write(blobs_dir.join("sha256_a"), b"a").unwrap();
+
+        let (config, root_hash) = load_nydus_config(dir.path()).unwrap();
+        assert_eq!(config.blob_digests, vec!["sha256_a", "sha256_b"]);
+        assert!(!root_hash.is_empty());
+    }
+}
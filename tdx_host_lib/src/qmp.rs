@@ -0,0 +1,147 @@
+//! Minimal QMP (QEMU Machine Protocol) client: a line-delimited JSON stream over the
+//! unix socket QEMU opens when launched with `-qmp unix:<path>,server,nowait`.
+//!
+//! On connect the server sends a `{"QMP": {...}}` greeting. The client must then send
+//! `{"execute": "qmp_capabilities"}` and receive `{"return": {}}` before issuing any other
+//! command. After that, commands are `{"execute": "<cmd>", "arguments": {...}}` and
+//! responses are either `{"return": ...}` or `{"error": {"class", "desc"}}`. Asynchronous
+//! `{"event": ...}` messages (SHUTDOWN, RESET, ...) can arrive interleaved with command
+//! replies, so they're buffered separately rather than mistaken for a reply.
+
+use anyhow::{anyhow, Context, Result};
+use serde_json::{json, Value};
+use std::collections::VecDeque;
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::UnixStream;
+use std::path::Path;
+use std::thread::sleep;
+use std::time::Duration;
+
+/// A connected, capabilities-negotiated QMP session.
+pub struct QmpClient {
+    stream: UnixStream,
+    reader: BufReader<UnixStream>,
+    /// Events observed while waiting for a command reply, oldest first.
+    pending_events: VecDeque<Value>,
+}
+
+impl QmpClient {
+    /// Connects to the QMP unix socket at `path` and performs the capabilities handshake.
+    pub fn connect(path: &Path) -> Result<Self> {
+        let stream = UnixStream::connect(path)
+            .with_context(|| format!("Failed to connect to QMP socket {}", path.display()))?;
+        let reader = BufReader::new(
+            stream
+                .try_clone()
+                .context("Failed to clone QMP socket for reading")?,
+        );
+
+        let mut client = Self {
+            stream,
+            reader,
+            pending_events: VecDeque::new(),
+        };
+
+        // Consume the greeting, e.g. `{"QMP": {"version": ..., "capabilities": []}}`.
+        let greeting = client.read_message()?;
+        if greeting.get("QMP").is_none() {
+            return Err(anyhow!("Unexpected QMP greeting: {}", greeting));
+        }
+
+        client.execute("qmp_capabilities", None)?;
+        Ok(client)
+    }
+
+    /// Sends a command and blocks for its reply, buffering any interleaved events.
+    pub fn execute(&mut self, command: &str, arguments: Option<Value>) -> Result<Value> {
+        let mut request = json!({ "execute": command });
+        if let Some(args) = arguments {
+            request["arguments"] = args;
+        }
+        let line = serde_json::to_string(&request)? + "\n";
+        self.stream
+            .write_all(line.as_bytes())
+            .with_context(|| format!("Failed to send QMP command '{}'", command))?;
+
+        loop {
+            let message = self.read_message()?;
+            if message.get("event").is_some() {
+                self.pending_events.push_back(message);
+                continue;
+            }
+            if let Some(result) = message.get("return") {
+                return Ok(result.clone());
+            }
+            if let Some(error) = message.get("error") {
+                let desc = error
+                    .get("desc")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("unknown QMP error");
+                return Err(anyhow!("QMP command '{}' failed: {}", command, desc));
+            }
+            return Err(anyhow!("Unexpected QMP message: {}", message));
+        }
+    }
+
+    /// Drains and returns any events buffered while waiting on command replies.
+    pub fn take_pending_events(&mut self) -> Vec<Value> {
+        self.pending_events.drain(..).collect()
+    }
+
+    fn read_message(&mut self) -> Result<Value> {
+        let mut line = String::new();
+        let n = self
+            .reader
+            .read_line(&mut line)
+            .context("Failed to read from QMP socket")?;
+        if n == 0 {
+            return Err(anyhow!("QMP socket closed unexpectedly"));
+        }
+        serde_json::from_str(line.trim_end())
+            .with_context(|| format!("Failed to parse QMP message: {}", line.trim_end()))
+    }
+
+    pub fn query_status(&mut self) -> Result<Value> {
+        self.execute("query-status", None)
+    }
+
+    pub fn stop(&mut self) -> Result<()> {
+        self.execute("stop", None).map(|_| ())
+    }
+
+    pub fn cont(&mut self) -> Result<()> {
+        self.execute("cont", None).map(|_| ())
+    }
+
+    pub fn system_powerdown(&mut self) -> Result<()> {
+        self.execute("system_powerdown", None).map(|_| ())
+    }
+
+    pub fn query_pci(&mut self) -> Result<Value> {
+        self.execute("query-pci", None)
+    }
+
+    /// Dumps VM state to `path` via QEMU's exec-migration protocol, then polls
+    /// `query-migrate` until the transfer completes.
+    pub fn migrate_to_file(&mut self, path: &Path) -> Result<()> {
+        self.execute(
+            "migrate",
+            Some(json!({ "uri": format!("exec:cat > {}", path.display()) })),
+        )?;
+
+        loop {
+            let status = self.execute("query-migrate", None)?;
+            match status.get("status").and_then(|v| v.as_str()) {
+                Some("completed") => return Ok(()),
+                Some("failed") => {
+                    let desc = status
+                        .get("error-desc")
+                        .and_then(|v| v.as_str())
+                        .unwrap_or("unknown error");
+                    return Err(anyhow!("Migration to {} failed: {}", path.display(), desc));
+                }
+                _ => sleep(Duration::from_millis(200)),
+            }
+        }
+    }
+}
@@ -0,0 +1,73 @@
+//! Pluggable VMM backend: instead of `DStackManager` being hard-wired to spawn
+//! `qemu-system-x86_64`, launches go through a `VmmBackend` implementation. The default
+//! [`QemuBackend`] delegates to the existing process-argv launch path; [`cloud_hypervisor`]
+//! drives cloud-hypervisor's HTTP-over-unix-socket API instead, so the same manifest can
+//! target either backend.
+
+use crate::{DStackManager, VMConfig};
+use anyhow::Result;
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+pub mod cloud_hypervisor;
+
+pub use cloud_hypervisor::CloudHypervisorBackend;
+
+/// A confidential-VM launcher, independent of the underlying hypervisor process/API.
+pub trait VmmBackend {
+    /// Prepares the backend to launch `vm_config`, whose rootfs lives at `image_path`.
+    fn setup(&mut self, vm_config: &VMConfig, image_path: &Path) -> Result<()>;
+    fn boot(&mut self) -> Result<()>;
+    fn pause(&mut self) -> Result<()>;
+    fn resume(&mut self) -> Result<()>;
+    fn shutdown(&mut self) -> Result<()>;
+    fn status(&mut self) -> Result<Value>;
+}
+
+/// The default backend: spawns QEMU via [`DStackManager::run_instance`] and controls it
+/// over the QMP socket the same way `DStackManager`'s own methods do.
+pub struct QemuBackend {
+    manager: Arc<DStackManager>,
+    vm_dir: PathBuf,
+    host_port: u16,
+}
+
+impl QemuBackend {
+    pub fn new(manager: Arc<DStackManager>, vm_dir: PathBuf, host_port: u16) -> Self {
+        Self {
+            manager,
+            vm_dir,
+            host_port,
+        }
+    }
+}
+
+impl VmmBackend for QemuBackend {
+    fn setup(&mut self, _vm_config: &VMConfig, _image_path: &Path) -> Result<()> {
+        // `setup_instance` has already written app-compose.json/config.json/vm-manifest.json;
+        // nothing else to prepare before spawning QEMU.
+        Ok(())
+    }
+
+    fn boot(&mut self) -> Result<()> {
+        self.manager
+            .run_instance(&self.vm_dir, self.host_port, None, None, None)
+    }
+
+    fn pause(&mut self) -> Result<()> {
+        self.manager.pause(&self.vm_dir)
+    }
+
+    fn resume(&mut self) -> Result<()> {
+        self.manager.resume(&self.vm_dir)
+    }
+
+    fn shutdown(&mut self) -> Result<()> {
+        self.manager.powerdown(&self.vm_dir)
+    }
+
+    fn status(&mut self) -> Result<Value> {
+        self.manager.status(&self.vm_dir)
+    }
+}
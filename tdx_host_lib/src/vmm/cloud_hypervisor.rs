@@ -0,0 +1,155 @@
+//! A [`VmmBackend`](super::VmmBackend) that drives [cloud-hypervisor's HTTP
+//! API](https://www.cloudhypervisor.org/docs/api/) over its unix control socket instead of
+//! launching a process from argv. Requests are plain HTTP/1.1 sent directly over a
+//! [`UnixStream`]: `PUT /api/v1/vm.create` with a JSON body, `PUT /api/v1/vm.boot`,
+//! `PUT /api/v1/vm.pause`, `PUT /api/v1/vm.resume`, `PUT /api/v1/vm.shutdown` and
+//! `GET /api/v1/vm.info`. Responses are read by parsing the status line, then the
+//! `Content-Length` header, then exactly that many body bytes.
+
+use super::VmmBackend;
+use crate::VMConfig;
+use anyhow::{anyhow, Context, Result};
+use serde_json::{json, Value};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+
+/// Talks to a single cloud-hypervisor instance over its API socket.
+pub struct CloudHypervisorBackend {
+    api_socket: PathBuf,
+}
+
+impl CloudHypervisorBackend {
+    /// `api_socket` is the path cloud-hypervisor was (or will be) started with via
+    /// `--api-socket <path>`.
+    pub fn new(api_socket: PathBuf) -> Self {
+        Self { api_socket }
+    }
+
+    fn request(&self, method: &str, path: &str, body: Option<&Value>) -> Result<Value> {
+        let mut stream = UnixStream::connect(&self.api_socket).with_context(|| {
+            format!(
+                "Failed to connect to cloud-hypervisor API socket {}",
+                self.api_socket.display()
+            )
+        })?;
+
+        let payload = body.map(serde_json::to_vec).transpose()?.unwrap_or_default();
+        let mut request = format!(
+            "{} {} HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n",
+            method,
+            path,
+            payload.len()
+        )
+        .into_bytes();
+        request.extend_from_slice(&payload);
+
+        stream
+            .write_all(&request)
+            .with_context(|| format!("Failed to send {} {} to cloud-hypervisor", method, path))?;
+
+        let mut reader = BufReader::new(stream);
+
+        let mut status_line = String::new();
+        reader
+            .read_line(&mut status_line)
+            .context("Failed to read cloud-hypervisor response status line")?;
+        let status = status_line
+            .split_whitespace()
+            .nth(1)
+            .ok_or_else(|| anyhow!("Malformed cloud-hypervisor status line: {}", status_line))?;
+
+        let mut content_length = 0usize;
+        loop {
+            let mut header = String::new();
+            reader
+                .read_line(&mut header)
+                .context("Failed to read cloud-hypervisor response headers")?;
+            let header = header.trim_end();
+            if header.is_empty() {
+                break;
+            }
+            if let Some((name, value)) = header.split_once(':') {
+                if name.eq_ignore_ascii_case("content-length") {
+                    content_length = value.trim().parse().with_context(|| {
+                        format!("Invalid Content-Length header: {}", value)
+                    })?;
+                }
+            }
+        }
+
+        let mut body_bytes = vec![0u8; content_length];
+        reader
+            .read_exact(&mut body_bytes)
+            .context("Failed to read cloud-hypervisor response body")?;
+
+        if !status.starts_with('2') {
+            let text = String::from_utf8_lossy(&body_bytes);
+            return Err(anyhow!(
+                "cloud-hypervisor {} {} returned {}: {}",
+                method,
+                path,
+                status,
+                text
+            ));
+        }
+
+        if body_bytes.is_empty() {
+            Ok(Value::Null)
+        } else {
+            serde_json::from_slice(&body_bytes)
+                .with_context(|| format!("Failed to parse cloud-hypervisor response to {} {}", method, path))
+        }
+    }
+
+    /// Maps our [`VMConfig`] onto cloud-hypervisor's `vm.create` request body.
+    fn vm_config_payload(vm_config: &VMConfig, image_path: &Path) -> Value {
+        let disk_path = image_path.join("rootfs.img");
+        let mut devices = Vec::new();
+        for gpu_id in &vm_config.gpu {
+            devices.push(json!({ "path": format!("/sys/bus/pci/devices/{}", gpu_id) }));
+        }
+
+        json!({
+            "cpus": { "boot_vcpus": vm_config.vcpu, "max_vcpus": vm_config.vcpu },
+            "memory": { "size": vm_config.memory * 1024 * 1024 },
+            "disks": [{ "path": disk_path.display().to_string() }],
+            "devices": devices,
+            "net": vm_config.port_map.iter().map(|pm| {
+                json!({ "tap": null, "ip": pm.address, "mask": "255.255.255.0" })
+            }).collect::<Vec<_>>(),
+        })
+    }
+}
+
+impl VmmBackend for CloudHypervisorBackend {
+    fn setup(&mut self, vm_config: &VMConfig, image_path: &Path) -> Result<()> {
+        let payload = Self::vm_config_payload(vm_config, image_path);
+        self.request("PUT", "/api/v1/vm.create", Some(&payload))?;
+        Ok(())
+    }
+
+    fn boot(&mut self) -> Result<()> {
+        self.request("PUT", "/api/v1/vm.boot", None)?;
+        Ok(())
+    }
+
+    fn pause(&mut self) -> Result<()> {
+        self.request("PUT", "/api/v1/vm.pause", None)?;
+        Ok(())
+    }
+
+    fn resume(&mut self) -> Result<()> {
+        self.request("PUT", "/api/v1/vm.resume", None)?;
+        Ok(())
+    }
+
+    fn shutdown(&mut self) -> Result<()> {
+        self.request("PUT", "/api/v1/vm.shutdown", None)?;
+        Ok(())
+    }
+
+    fn status(&mut self) -> Result<Value> {
+        self.request("GET", "/api/v1/vm.info", None)
+    }
+}
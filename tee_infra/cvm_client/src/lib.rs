@@ -1,18 +1,20 @@
 use anyhow::{Context, Result};
 use base64::Engine;
+use dcap_qvl::collateral::Collateral;
 use dcap_qvl::collateral::get_collateral;
-use dcap_qvl::collateral::get_collateral_and_verify;
 use dcap_qvl::verify::VerifiedReport;
 use dcap_qvl::verify::verify;
 use near_auth::AuthData;
 use reqwest::{Client, header};
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use rustls::{DigitallySignedStruct, SignatureScheme};
 use serde::{Deserialize, Serialize};
-use sha2::{Digest, Sha512};
+use sha2::{Digest, Sha256, Sha512};
 use std::collections::HashMap;
 use std::pin::Pin;
-use std::process::Command;
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
-use tempfile::NamedTempFile;
 use url::Url;
 
 /// Request to assign an agent to a CVM
@@ -76,29 +78,974 @@ pub struct TD10Report {
     // Add other fields as needed
 }
 
+/// SHA-256 of a certificate's DER-encoded SubjectPublicKeyInfo (SPKI), the value this crate
+/// pins on instead of the whole certificate -- it survives a cert being re-issued off the same
+/// key pair, which a CVM runner legitimately does on restart.
+type SpkiHash = [u8; 32];
+
+/// A `rustls` [`ServerCertVerifier`] that captures the leaf certificate's SPKI hash on every
+/// handshake instead of validating it against a root store -- the CVM's certificate is
+/// self-signed and its trust comes from the TDX quote, not a CA.
+///
+/// Before [`CvmClient::attest`] succeeds, [`Self::pinned`] is empty and every handshake is
+/// accepted (capturing its SPKI hash so `get_certificate_hash` can feed it into the quote
+/// verification). Once `attest` calls [`Self::pin`], any later handshake whose leaf SPKI hash
+/// doesn't match the pinned one is a hard failure -- a rotated certificate must be re-attested,
+/// not silently trusted.
+#[derive(Debug)]
+struct PinningCertVerifier {
+    /// SPKI hash captured from the most recent handshake, read by `get_certificate_hash`.
+    captured: Mutex<Option<SpkiHash>>,
+    /// SPKI hash attestation verified as belonging to the CVM's TDX quote, set by `pin`. `None`
+    /// until the first successful `attest()`.
+    pinned: Mutex<Option<SpkiHash>>,
+    provider: Arc<rustls::crypto::CryptoProvider>,
+}
+
+impl PinningCertVerifier {
+    fn new() -> Self {
+        Self {
+            captured: Mutex::new(None),
+            pinned: Mutex::new(None),
+            provider: Arc::new(rustls::crypto::ring::default_provider()),
+        }
+    }
+
+    /// The SPKI hash captured from the most recent handshake, for `get_certificate_hash` to feed
+    /// into `generate_sha512_hash`.
+    fn captured_hash(&self) -> Option<SpkiHash> {
+        *self.captured.lock().expect("PinningCertVerifier mutex poisoned")
+    }
+
+    /// Pins `hash` as the only SPKI hash future handshakes may present. Called once attestation
+    /// against the current certificate succeeds.
+    fn pin(&self, hash: SpkiHash) {
+        *self.pinned.lock().expect("PinningCertVerifier mutex poisoned") = Some(hash);
+    }
+}
+
+impl ServerCertVerifier for PinningCertVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> std::result::Result<ServerCertVerified, rustls::Error> {
+        let spki_hash = spki_sha256(end_entity)
+            .map_err(|e| rustls::Error::General(format!("Failed to parse CVM certificate: {}", e)))?;
+
+        *self.captured.lock().expect("PinningCertVerifier mutex poisoned") = Some(spki_hash);
+
+        if let Some(pinned) = *self.pinned.lock().expect("PinningCertVerifier mutex poisoned") {
+            if pinned != spki_hash {
+                return Err(rustls::Error::General(
+                    "CVM certificate's public key changed since attestation; re-attest before trusting it again".to_string(),
+                ));
+            }
+        }
+
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> std::result::Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls12_signature(
+            message,
+            cert,
+            dss,
+            &self.provider.signature_verification_algorithms,
+        )
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> std::result::Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls13_signature(
+            message,
+            cert,
+            dss,
+            &self.provider.signature_verification_algorithms,
+        )
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.provider.signature_verification_algorithms.supported_schemes()
+    }
+}
+
+/// Extracts the DER-encoded leaf certificate's SubjectPublicKeyInfo and returns its SHA-256.
+fn spki_sha256(cert: &CertificateDer<'_>) -> Result<SpkiHash> {
+    let (_, parsed) =
+        x509_parser::parse_x509_certificate(cert).context("Failed to parse leaf certificate")?;
+
+    let mut hasher = Sha256::new();
+    hasher.update(parsed.tbs_certificate.subject_pki.raw);
+    let hash = hasher.finalize();
+
+    let mut result = SpkiHash::default();
+    result.copy_from_slice(&hash);
+    Ok(result)
+}
+
+/// An in-memory client certificate chain and private key, presented to the server as a TLS
+/// client identity by [`CvmClient::with_identity`] for deployments that enforce mTLS.
+#[derive(Debug, Clone)]
+pub struct ClientIdentity {
+    cert_chain_pem: Vec<u8>,
+    private_key_pem: Vec<u8>,
+}
+
+impl ClientIdentity {
+    /// Builds an identity from PEM-encoded bytes already in memory.
+    pub fn from_pem(cert_chain_pem: Vec<u8>, private_key_pem: Vec<u8>) -> Self {
+        Self {
+            cert_chain_pem,
+            private_key_pem,
+        }
+    }
+
+    /// Reads a PEM cert chain and private key from disk.
+    pub fn from_files(cert_chain_path: &std::path::Path, private_key_path: &std::path::Path) -> Result<Self> {
+        let cert_chain_pem = std::fs::read(cert_chain_path).with_context(|| {
+            format!("Failed to read client cert chain {}", cert_chain_path.display())
+        })?;
+        let private_key_pem = std::fs::read(private_key_path).with_context(|| {
+            format!("Failed to read client private key {}", private_key_path.display())
+        })?;
+        Ok(Self::from_pem(cert_chain_pem, private_key_pem))
+    }
+
+    /// Parses the PEM bytes into the `rustls` types `with_client_auth_cert` expects.
+    fn into_rustls_parts(
+        self,
+    ) -> Result<(
+        Vec<CertificateDer<'static>>,
+        rustls::pki_types::PrivateKeyDer<'static>,
+    )> {
+        let cert_chain = rustls_pemfile::certs(&mut self.cert_chain_pem.as_slice())
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .context("Failed to parse client certificate chain PEM")?;
+        if cert_chain.is_empty() {
+            return Err(anyhow::anyhow!("Client certificate chain PEM has no certificates"));
+        }
+
+        let private_key = rustls_pemfile::private_key(&mut self.private_key_pem.as_slice())
+            .context("Failed to parse client private key PEM")?
+            .ok_or_else(|| anyhow::anyhow!("Client private key PEM has no private key"))?;
+
+        Ok((cert_chain, private_key))
+    }
+}
+
+/// A non-2xx HTTP response, carrying the body so callers (and [`CvmClient::should_reattest`])
+/// see the CVM's actual error message and status rather than just an opaque failure.
+#[derive(Debug)]
+struct StatusError {
+    status: reqwest::StatusCode,
+    body: String,
+}
+
+impl std::fmt::Display for StatusError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Request failed with status {}: {}", self.status, self.body)
+    }
+}
+
+impl std::error::Error for StatusError {}
+
+/// Where [`CvmClient::with_trust_cache`] persists attested trust records, and how long an entry
+/// is honored before it must be re-attested even if the pinned certificate hasn't changed.
+#[derive(Debug, Clone)]
+pub struct TrustCacheConfig {
+    pub dir: std::path::PathBuf,
+    pub ttl: Duration,
+}
+
+/// One on-disk trust record, stored in a file named after the host it was attested against: the
+/// SPKI hash pinned for it, the TD10 report data that attestation verified, and when that happened.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct TrustCacheEntry {
+    spki_hash: String,
+    report_data: String,
+    attested_at_unix_secs: u64,
+}
+
+/// On-disk cache of previously attested hosts, keyed by `host:port`, so a new [`CvmClient`]
+/// against an endpoint it has already attested can skip the quote fetch/verify round trip until
+/// the entry's TTL elapses. [`PinningCertVerifier`] still enforces the cached pin on every
+/// handshake, so a rotated certificate is caught (and [`CvmClient::make_request`]'s retry re-runs
+/// `attest()`) even if the cache entry hasn't expired yet.
+struct TrustCache {
+    dir: std::path::PathBuf,
+    ttl: Duration,
+}
+
+impl TrustCache {
+    fn new(config: TrustCacheConfig) -> Self {
+        Self {
+            dir: config.dir,
+            ttl: config.ttl,
+        }
+    }
+
+    fn path_for(&self, host: &str) -> std::path::PathBuf {
+        self.dir.join(format!("{}.json", host.replace([':', '/'], "_")))
+    }
+
+    /// The cached entry for `host`, if one exists and hasn't outlived `self.ttl`.
+    fn load(&self, host: &str) -> Option<TrustCacheEntry> {
+        let contents = std::fs::read_to_string(self.path_for(host)).ok()?;
+        let entry: TrustCacheEntry = serde_json::from_str(&contents).ok()?;
+        let age_secs = now_unix_secs().saturating_sub(entry.attested_at_unix_secs);
+        (age_secs <= self.ttl.as_secs()).then_some(entry)
+    }
+
+    fn store(&self, host: &str, entry: &TrustCacheEntry) -> Result<()> {
+        std::fs::create_dir_all(&self.dir).with_context(|| {
+            format!("Failed to create trust cache directory {}", self.dir.display())
+        })?;
+        let contents =
+            serde_json::to_string(entry).context("Failed to serialize trust cache entry")?;
+        std::fs::write(self.path_for(host), contents)
+            .with_context(|| format!("Failed to write trust cache entry for {}", host))
+    }
+
+    fn clear(&self, host: &str) {
+        let _ = std::fs::remove_file(self.path_for(host));
+    }
+}
+
+fn now_unix_secs() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// The platform measurements and attestation status parsed out of a verified TDX quote. Returned
+/// by [`CvmClient::last_measurements`] so callers can log or audit what was attested rather than
+/// only getting a boolean success, and checked against a [`MeasurementPolicy`] by
+/// [`CvmClient::attest`].
+#[derive(Debug, Clone)]
+pub struct QuoteMeasurements {
+    pub mr_td: [u8; 48],
+    pub rtmr0: [u8; 48],
+    pub rtmr1: [u8; 48],
+    pub rtmr2: [u8; 48],
+    pub rtmr3: [u8; 48],
+    pub mr_config_id: [u8; 48],
+    pub mr_owner: [u8; 48],
+    pub tcb_status: String,
+}
+
+impl QuoteMeasurements {
+    fn from_verified_report(verified_report: &VerifiedReport) -> Result<Self> {
+        let td10 = verified_report
+            .report
+            .as_td10()
+            .ok_or_else(|| anyhow::anyhow!("Quote is not a TD1.0 report"))?;
+        Ok(Self {
+            mr_td: td10.mr_td,
+            rtmr0: td10.rt_mr0,
+            rtmr1: td10.rt_mr1,
+            rtmr2: td10.rt_mr2,
+            rtmr3: td10.rt_mr3,
+            mr_config_id: td10.mr_config_id,
+            mr_owner: td10.mr_owner,
+            tcb_status: verified_report.status.clone(),
+        })
+    }
+}
+
+/// One "known-good" set of platform measurements a [`MeasurementPolicy`] checks a quote against.
+/// Fields left `None` aren't checked, so a policy can pin only the registers that matter for a
+/// given deployment (e.g. `mr_td` but not `mr_owner`).
+#[derive(Debug, Clone, Default)]
+pub struct ExpectedMeasurement {
+    pub mr_td: Option<[u8; 48]>,
+    pub rtmr0: Option<[u8; 48]>,
+    pub rtmr1: Option<[u8; 48]>,
+    pub rtmr2: Option<[u8; 48]>,
+    pub rtmr3: Option<[u8; 48]>,
+    pub mr_config_id: Option<[u8; 48]>,
+    pub mr_owner: Option<[u8; 48]>,
+}
+
+impl ExpectedMeasurement {
+    fn check(&self, actual: &QuoteMeasurements) -> std::result::Result<(), MeasurementMismatch> {
+        Self::check_register("mr_td", self.mr_td, actual.mr_td)?;
+        Self::check_register("rtmr0", self.rtmr0, actual.rtmr0)?;
+        Self::check_register("rtmr1", self.rtmr1, actual.rtmr1)?;
+        Self::check_register("rtmr2", self.rtmr2, actual.rtmr2)?;
+        Self::check_register("rtmr3", self.rtmr3, actual.rtmr3)?;
+        Self::check_register("mr_config_id", self.mr_config_id, actual.mr_config_id)?;
+        Self::check_register("mr_owner", self.mr_owner, actual.mr_owner)?;
+        Ok(())
+    }
+
+    fn check_register(
+        register: &'static str,
+        expected: Option<[u8; 48]>,
+        actual: [u8; 48],
+    ) -> std::result::Result<(), MeasurementMismatch> {
+        match expected {
+            Some(expected) if expected != actual => Err(MeasurementMismatch {
+                register,
+                expected: hex::encode(expected),
+                actual: hex::encode(actual),
+            }),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Which register an [`ExpectedMeasurement`] check failed on, and its expected vs. actual
+/// (hex-encoded) digest -- so a policy failure names exactly what changed instead of a bare
+/// boolean.
+#[derive(Debug)]
+pub struct MeasurementMismatch {
+    pub register: &'static str,
+    pub expected: String,
+    pub actual: String,
+}
+
+impl std::fmt::Display for MeasurementMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Measurement mismatch on {}: expected {}, got {}",
+            self.register, self.expected, self.actual
+        )
+    }
+}
+
+impl std::error::Error for MeasurementMismatch {}
+
+/// Allowlist [`CvmClient::attest`] checks a quote's [`QuoteMeasurements`] against: either they
+/// must match one specific [`ExpectedMeasurement`] exactly, or match any one of a set of
+/// known-good digests -- for rolling image updates where several image versions are
+/// simultaneously acceptable.
+#[derive(Debug, Clone)]
+pub enum MeasurementPolicy {
+    Exact(ExpectedMeasurement),
+    AnyOf(Vec<ExpectedMeasurement>),
+}
+
+impl MeasurementPolicy {
+    fn check(&self, actual: &QuoteMeasurements) -> std::result::Result<(), MeasurementMismatch> {
+        match self {
+            MeasurementPolicy::Exact(expected) => expected.check(actual),
+            MeasurementPolicy::AnyOf(candidates) => {
+                let mut last_err = None;
+                for candidate in candidates {
+                    match candidate.check(actual) {
+                        Ok(()) => return Ok(()),
+                        Err(e) => last_err = Some(e),
+                    }
+                }
+                Err(last_err.unwrap_or(MeasurementMismatch {
+                    register: "mr_td",
+                    expected: "<no candidates in allowlist>".to_string(),
+                    actual: hex::encode(actual.mr_td),
+                }))
+            }
+        }
+    }
+}
+
+/// The MRTD/RTMR measurement [`CvmClient::verify_quote_quorum`] groups verified quotes by, to
+/// tell whether independently attested nodes agree on the same workload.
+///
+/// Note: this doesn't include `mr_config_id`/`mr_owner`, which vary with launch-time config
+/// rather than the workload itself, so two nodes running the same image with different
+/// `mr_owner` still count as agreeing.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct QuorumMeasurementKey {
+    mr_td: [u8; 48],
+    rtmr0: [u8; 48],
+    rtmr1: [u8; 48],
+    rtmr2: [u8; 48],
+    rtmr3: [u8; 48],
+}
+
+impl From<&QuoteMeasurements> for QuorumMeasurementKey {
+    fn from(measurements: &QuoteMeasurements) -> Self {
+        Self {
+            mr_td: measurements.mr_td,
+            rtmr0: measurements.rtmr0,
+            rtmr1: measurements.rtmr1,
+            rtmr2: measurements.rtmr2,
+            rtmr3: measurements.rtmr3,
+        }
+    }
+}
+
+/// One quote's outcome within a [`QuorumResult`]: its position in the input slice, and whether it
+/// verified (and what it measured) or failed.
+#[derive(Debug, Clone)]
+pub struct QuorumMember {
+    pub index: usize,
+    pub outcome: QuorumMemberOutcome,
+}
+
+/// How one [`QuorumMember`] fared in [`CvmClient::verify_quote_quorum`].
+#[derive(Debug, Clone)]
+pub enum QuorumMemberOutcome {
+    /// Verified via [`CvmClient::verify_quote_from_pccs`] and reported this measurement, plus the
+    /// SHA-256 of its PCK leaf certificate's bytes (see [`pck_leaf_cert_fingerprint`]) --
+    /// `None` if the quote's cert chain didn't contain a parseable PEM certificate. Two members
+    /// with the same `Some` fingerprint are the same physical platform and
+    /// [`CvmClient::verify_quote_quorum`] only counts one of them toward the threshold.
+    Verified {
+        measurements: QuoteMeasurements,
+        pck_cert_fingerprint: Option<[u8; 32]>,
+    },
+    /// Failed verification, was rejected by the client's TCB policy, had an `expected_mrtd`
+    /// mismatch, or wasn't a TD1.0 report; carries why.
+    Failed(String),
+}
+
+/// Extracts the first PEM-encoded certificate embedded in a raw DCAP quote's `QE Cert Data`
+/// (the leaf PCK certificate, for ECDSA quotes using cert type 5) and returns the SHA-256 of its
+/// raw bytes -- a cheap stand-in for parsing the platform instance ID out of the cert's SGX
+/// extensions, used by [`CvmClient::verify_quote_quorum`] to tell two submissions of the same
+/// node's quote apart from two distinct nodes that happen to report identical measurements.
+/// Returns `None` if no `-----BEGIN CERTIFICATE-----` block is found (e.g. a non-ECDSA quote or a
+/// cert_data type this crate doesn't expect).
+fn pck_leaf_cert_fingerprint(raw_quote: &[u8]) -> Option<[u8; 32]> {
+    const PEM_BEGIN: &[u8] = b"-----BEGIN CERTIFICATE-----";
+    const PEM_END: &[u8] = b"-----END CERTIFICATE-----";
+
+    let start = raw_quote
+        .windows(PEM_BEGIN.len())
+        .position(|w| w == PEM_BEGIN)?;
+    let end_offset = raw_quote[start..]
+        .windows(PEM_END.len())
+        .position(|w| w == PEM_END)?;
+    let end = start + end_offset + PEM_END.len();
+
+    let mut hasher = Sha256::new();
+    hasher.update(&raw_quote[start..end]);
+    Some(hasher.finalize().into())
+}
+
+/// Result of [`CvmClient::verify_quote_quorum`]: whether at least `threshold` independently
+/// verified nodes agreed on an identical measurement, which measurement that was, and how every
+/// submitted quote fared, so a caller can log which nodes disagreed or failed outright.
+#[derive(Debug, Clone)]
+pub struct QuorumResult {
+    pub satisfied: bool,
+    pub winning_measurement: Option<QuoteMeasurements>,
+    pub members: Vec<QuorumMember>,
+}
+
+/// The pure, network-free half of [`CvmClient::verify_quote_quorum`]: groups already-verified
+/// `outcomes` by measurement, deduping same-platform submissions by PCK cert fingerprint, and
+/// picks a winning measurement if any group reaches `threshold`. Split out so this tallying
+/// logic (including the tie-break and dedup rules) can be exercised directly in tests without a
+/// PCCS round trip.
+fn tally_quorum_outcomes(outcomes: Vec<QuorumMemberOutcome>, threshold: usize) -> QuorumResult {
+    // Distinct platforms (by PCK cert fingerprint) seen so far within a measurement group, so a
+    // repeated fingerprint is recorded once here but the member itself is still kept in
+    // `members` below for the caller's full per-quote report.
+    let mut seen_fingerprints: HashMap<QuorumMeasurementKey, std::collections::HashSet<[u8; 32]>> =
+        HashMap::new();
+    let mut tally: HashMap<QuorumMeasurementKey, Vec<usize>> = HashMap::new();
+    let members: Vec<QuorumMember> = outcomes
+        .into_iter()
+        .enumerate()
+        .map(|(index, outcome)| {
+            if let QuorumMemberOutcome::Verified {
+                measurements,
+                pck_cert_fingerprint,
+            } = &outcome
+            {
+                let key = QuorumMeasurementKey::from(measurements);
+                // A fingerprint we couldn't extract can't be deduped against anything, so it
+                // always counts as a distinct platform (same as today's behavior for it).
+                let is_new_platform = match pck_cert_fingerprint {
+                    Some(fp) => seen_fingerprints.entry(key.clone()).or_default().insert(*fp),
+                    None => true,
+                };
+                if is_new_platform {
+                    tally.entry(key).or_default().push(index);
+                }
+            }
+            QuorumMember { index, outcome }
+        })
+        .collect();
+
+    // Sorted rather than `max_by_key` over the `HashMap`'s iteration order, which is randomized
+    // per-process: a tie between two equally-sized, genuinely disagreeing groups must resolve
+    // the same way on every call instead of nondeterministically picking either one as the
+    // "winner".
+    let mut groups: Vec<(QuorumMeasurementKey, Vec<usize>)> = tally.into_iter().collect();
+    groups.sort_by(|(_, a), (_, b)| {
+        b.len()
+            .cmp(&a.len())
+            .then_with(|| a.first().cmp(&b.first()))
+    });
+
+    let winning_measurement = groups
+        .into_iter()
+        .find(|(_, indices)| indices.len() >= threshold)
+        .and_then(|(_, indices)| {
+            indices.first().and_then(|&i| match &members[i].outcome {
+                QuorumMemberOutcome::Verified { measurements, .. } => Some(measurements.clone()),
+                QuorumMemberOutcome::Failed(_) => None,
+            })
+        });
+
+    QuorumResult {
+        satisfied: winning_measurement.is_some(),
+        winning_measurement,
+        members,
+    }
+}
+
+/// A quote's platform TCB status, evaluated by dcap_qvl against the TCB Info fetched alongside
+/// its collateral. Mirrors Intel's TCB Info `tcbStatus` enum.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TcbStatus {
+    UpToDate,
+    OutOfDate,
+    ConfigurationNeeded,
+    SwHardeningNeeded,
+    ConfigurationAndSwHardeningNeeded,
+    Revoked,
+    /// A status string dcap_qvl surfaced that doesn't match any of the above -- forward
+    /// compatible with statuses Intel adds to the TCB Info schema in the future.
+    Unknown(String),
+}
+
+impl TcbStatus {
+    fn parse(raw: &str) -> Self {
+        match raw {
+            "UpToDate" => Self::UpToDate,
+            "OutOfDate" => Self::OutOfDate,
+            "ConfigurationNeeded" => Self::ConfigurationNeeded,
+            "SwHardeningNeeded" => Self::SwHardeningNeeded,
+            "ConfigurationAndSwHardeningNeeded" => Self::ConfigurationAndSwHardeningNeeded,
+            "Revoked" => Self::Revoked,
+            other => Self::Unknown(other.to_string()),
+        }
+    }
+}
+
+impl std::fmt::Display for TcbStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UpToDate => write!(f, "UpToDate"),
+            Self::OutOfDate => write!(f, "OutOfDate"),
+            Self::ConfigurationNeeded => write!(f, "ConfigurationNeeded"),
+            Self::SwHardeningNeeded => write!(f, "SwHardeningNeeded"),
+            Self::ConfigurationAndSwHardeningNeeded => {
+                write!(f, "ConfigurationAndSwHardeningNeeded")
+            }
+            Self::Revoked => write!(f, "Revoked"),
+            Self::Unknown(raw) => write!(f, "{}", raw),
+        }
+    }
+}
+
+/// The TCB status and applicable advisory IDs (CVEs) dcap_qvl evaluated for a verified quote's
+/// PCK certificate against the TCB Info fetched alongside its collateral. Returned by
+/// [`CvmClient::last_tcb_evaluation`] and checked against a [`TcbPolicy`] by
+/// [`CvmClient::attest`].
+#[derive(Debug, Clone)]
+pub struct TcbEvaluation {
+    pub status: TcbStatus,
+    pub advisory_ids: Vec<String>,
+}
+
+impl TcbEvaluation {
+    fn from_verified_report(verified_report: &VerifiedReport) -> Self {
+        Self {
+            status: TcbStatus::parse(&verified_report.status),
+            advisory_ids: verified_report.advisory_ids.clone(),
+        }
+    }
+}
+
+/// Which [`TcbStatus`] values [`CvmClient::attest`] accepts for a quote's platform -- e.g. a
+/// deployment that tolerates `ConfigurationNeeded` (a BIOS/firmware setting) but rejects
+/// `OutOfDate` or `Revoked`.
+#[derive(Debug, Clone)]
+pub struct TcbPolicy {
+    accepted: Vec<TcbStatus>,
+}
+
+impl TcbPolicy {
+    /// Accept exactly the given set of statuses.
+    pub fn accepting(accepted: Vec<TcbStatus>) -> Self {
+        Self { accepted }
+    }
+
+    /// Accept only `UpToDate`, rejecting every other status.
+    pub fn strict() -> Self {
+        Self::accepting(vec![TcbStatus::UpToDate])
+    }
+
+    fn check(&self, evaluation: &TcbEvaluation) -> std::result::Result<(), TcbPolicyViolation> {
+        if self.accepted.contains(&evaluation.status) {
+            Ok(())
+        } else {
+            Err(TcbPolicyViolation {
+                status: evaluation.status.clone(),
+                advisory_ids: evaluation.advisory_ids.clone(),
+            })
+        }
+    }
+}
+
+/// A quote's [`TcbStatus`] wasn't in the set a [`TcbPolicy`] accepts -- carries the status and
+/// any applicable advisory IDs so a caller can log which CVEs apply.
+#[derive(Debug)]
+pub struct TcbPolicyViolation {
+    pub status: TcbStatus,
+    pub advisory_ids: Vec<String>,
+}
+
+impl std::fmt::Display for TcbPolicyViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Quote TCB status {} not accepted by policy (advisories: {:?})",
+            self.status, self.advisory_ids
+        )
+    }
+}
+
+impl std::error::Error for TcbPolicyViolation {}
+
+/// `dcap_qvl::verify` rejected a quote, and the rejection looks like a certificate revocation (a
+/// serial number on the PCK CRL or the Intel SGX Root CA CRL) rather than an ordinary signature
+/// or TCB failure, surfaced here as a distinct, downcastable error (the same
+/// `anyhow::Error::downcast_ref` pattern `CvmClient`'s re-attestation check uses).
+///
+/// This classification is necessarily best-effort: `dcap_qvl::verify` returns an opaque
+/// `anyhow::Error` rather than a typed revocation variant, so [`verify_quote_with_collateral`]
+/// can only pattern-match its `Debug` output for the word "revoked" -- an unstable signal that
+/// could misclassify a future dcap_qvl error message, in either direction. `detail` is that raw
+/// `Debug` output; this crate doesn't independently re-walk the PCK chain against the CRLs, so it
+/// cannot reliably pull the offending certificate's serial number or issuer out as separate
+/// fields, and callers should not assume `detail` has a stable, parseable shape.
+#[derive(Debug)]
+pub struct QuoteRevoked {
+    pub detail: String,
+}
+
+impl std::fmt::Display for QuoteRevoked {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Quote certificate chain revoked: {}", self.detail)
+    }
+}
+
+impl std::error::Error for QuoteRevoked {}
+
+/// Intel's public PCCS, used as the default [`CollateralSource::Pccs`] `base_url` when the
+/// caller doesn't override it -- the same endpoint `get_collateral_and_verify` hit before
+/// [`CollateralSource`] existed.
+const DEFAULT_PCCS_URL: &str = "https://api.trustedservices.intel.com/sgx/certification/v4/";
+
+/// How long [`CvmClient::verify_quote_from_pccs`] waits for a PCCS response before giving up.
+const COLLATERAL_FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long a cached collateral entry is reused before [`CvmClient::verify_quote_from_pccs`]
+/// fetches a fresh one, configured by [`CollateralCacheConfig`].
+#[derive(Debug, Clone)]
+pub struct CollateralCacheConfig {
+    pub dir: std::path::PathBuf,
+    pub refresh_interval: Duration,
+}
+
+/// Where [`CvmClient::verify_quote_from_pccs`] gets a quote's collateral (TCB info, QE identity,
+/// CRLs, etc.) from.
+#[derive(Debug, Clone)]
+pub enum CollateralSource {
+    /// Fetches from a PCCS base URL (Intel's public one by default, overridable for a local
+    /// mirror or on-prem PCCS), optionally reusing a cached response per `cache`'s TTL so
+    /// repeated attestations against the same platform don't re-hit the PCCS.
+    Pccs {
+        base_url: String,
+        cache: Option<CollateralCacheConfig>,
+    },
+    /// Loads a self-contained collateral bundle from disk instead of the network, for fully
+    /// offline verification of a previously snapshotted quote.
+    Bundle { path: std::path::PathBuf },
+}
+
+impl Default for CollateralSource {
+    fn default() -> Self {
+        Self::Pccs {
+            base_url: DEFAULT_PCCS_URL.to_string(),
+            cache: None,
+        }
+    }
+}
+
+/// An on-disk collateral cache entry: the collateral itself and when it was fetched.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CachedCollateral {
+    collateral: Collateral,
+    fetched_at_unix_secs: u64,
+}
+
+/// On-disk cache of previously fetched collateral, keyed by the requesting quote -- this crate
+/// doesn't parse the FMSPC out of the quote's PCK certificate yet, so entries are keyed by a hash
+/// of the raw quote bytes instead, which still dedupes repeated attestations of the same running
+/// CVM.
+struct CollateralCache {
+    dir: std::path::PathBuf,
+    refresh_interval: Duration,
+}
+
+impl CollateralCache {
+    fn new(config: CollateralCacheConfig) -> Self {
+        Self {
+            dir: config.dir,
+            refresh_interval: config.refresh_interval,
+        }
+    }
+
+    fn key_for(raw_quote: &[u8]) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(raw_quote);
+        hex::encode(hasher.finalize())
+    }
+
+    fn path_for(&self, key: &str) -> std::path::PathBuf {
+        self.dir.join(format!("{}.json", key))
+    }
+
+    fn load(&self, key: &str) -> Option<Collateral> {
+        let contents = std::fs::read_to_string(self.path_for(key)).ok()?;
+        let cached: CachedCollateral = serde_json::from_str(&contents).ok()?;
+        let age_secs = now_unix_secs().saturating_sub(cached.fetched_at_unix_secs);
+        (age_secs <= self.refresh_interval.as_secs()).then_some(cached.collateral)
+    }
+
+    fn store(&self, key: &str, collateral: &Collateral) -> Result<()> {
+        std::fs::create_dir_all(&self.dir).with_context(|| {
+            format!("Failed to create collateral cache directory {}", self.dir.display())
+        })?;
+        let cached = CachedCollateral {
+            collateral: collateral.clone(),
+            fetched_at_unix_secs: now_unix_secs(),
+        };
+        let contents =
+            serde_json::to_string(&cached).context("Failed to serialize cached collateral")?;
+        std::fs::write(self.path_for(key), contents)
+            .with_context(|| format!("Failed to write cached collateral for {}", key))
+    }
+}
+
+/// Verifies `raw_quote` against a self-contained `collateral` bundle at `evaluation_time`, doing
+/// no network I/O -- the offline sibling of [`CvmClient::verify_quote_from_pccs`], for air-gapped
+/// verifiers or reproducible CI that pins a previously snapshotted collateral and wants a
+/// deterministic result regardless of when it's actually run.
+pub fn verify_quote_with_collateral(
+    raw_quote: &[u8],
+    collateral: &Collateral,
+    evaluation_time: std::time::SystemTime,
+) -> Result<VerifiedReport> {
+    verify(raw_quote, collateral, evaluation_time).map_err(|e| {
+        let detail = format!("{:?}", e);
+        if detail.to_lowercase().contains("revoked") {
+            anyhow::Error::new(QuoteRevoked { detail })
+        } else {
+            anyhow::anyhow!("Failed to verify quote: {}", detail)
+        }
+    })
+}
+
+/// Resolves the FMSPC and PCK cert chain embedded in `raw_quote` and downloads exactly the
+/// collateral bundle (TCB Info, QE identity, CRLs, root CA) it needs from `base_url`'s PCCS --
+/// the same fetch [`CvmClient::verify_quote_from_pccs`] does internally, exposed standalone so
+/// operators can snapshot collateral once and verify it later, offline, with
+/// [`verify_quote_with_collateral`].
+pub async fn fetch_collateral_from_pccs(raw_quote: &[u8], base_url: &str) -> Result<Collateral> {
+    get_collateral(base_url, raw_quote)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to get quote collateral: {:?}", e))
+}
+
+/// Bundles `CvmClient`'s optional construction-time capabilities so the `with_*` constructors
+/// don't have to keep growing a positional parameter per capability.
+struct ClientOptions {
+    identity: Option<ClientIdentity>,
+    trust_cache: Option<TrustCacheConfig>,
+    measurement_policy: Option<MeasurementPolicy>,
+    collateral_source: CollateralSource,
+    tcb_policy: Option<TcbPolicy>,
+}
+
+impl Default for ClientOptions {
+    fn default() -> Self {
+        Self {
+            identity: None,
+            trust_cache: None,
+            measurement_policy: None,
+            collateral_source: CollateralSource::default(),
+            tcb_policy: None,
+        }
+    }
+}
+
 /// Client for interacting with the CVM service
 pub struct CvmClient {
     url: String,
+    host: String,
     headers: header::HeaderMap,
     is_attested: bool,
-    cert_path: String,
+    verifier: Arc<PinningCertVerifier>,
     client: Client,
+    trust_cache: Option<TrustCache>,
+    measurement_policy: Option<MeasurementPolicy>,
+    collateral_source: CollateralSource,
+    tcb_policy: Option<TcbPolicy>,
+    last_measurements: Mutex<Option<QuoteMeasurements>>,
+    last_tcb_evaluation: Mutex<Option<TcbEvaluation>>,
 }
 
 impl CvmClient {
-    /// Create a new CVM client
+    /// Create a new CVM client. The CVM's certificate is self-signed, so instead of validating
+    /// it against a root store, every handshake is routed through a [`PinningCertVerifier`]:
+    /// it accepts the connection and captures the leaf certificate's SPKI hash (read back by
+    /// [`Self::get_certificate_hash`]) until [`Self::attest`] pins the attested hash, after
+    /// which a rotated certificate hard-fails the handshake instead of being silently trusted.
     pub fn new(url: &str, auth: &AuthData) -> Result<Self> {
-        // Parse URL to extract hostname and port
+        Self::with_options(url, auth, ClientOptions::default())
+    }
+
+    /// Same as [`Self::new`], but when `identity` is set, presents it as a client certificate
+    /// during the TLS handshake -- for CVM gateways that enforce mTLS in addition to the
+    /// `Bearer` `AuthData` header every request already carries.
+    pub fn with_identity(
+        url: &str,
+        auth: &AuthData,
+        identity: Option<ClientIdentity>,
+    ) -> Result<Self> {
+        Self::with_options(
+            url,
+            auth,
+            ClientOptions {
+                identity,
+                ..Default::default()
+            },
+        )
+    }
+
+    /// Same as [`Self::with_identity`], but additionally consults `trust_cache`: if a
+    /// non-expired entry exists for this host, its pinned SPKI hash is loaded up front and
+    /// `is_attested` starts `true`, skipping the initial quote fetch/verify. The cached pin is
+    /// still enforced by [`PinningCertVerifier`] on the first real handshake, so a certificate
+    /// that rotated since the entry was written surfaces as the usual pin-mismatch transport
+    /// error and [`Self::make_request`]'s retry re-attests and overwrites the stale entry.
+    pub fn with_trust_cache(
+        url: &str,
+        auth: &AuthData,
+        identity: Option<ClientIdentity>,
+        trust_cache: TrustCacheConfig,
+    ) -> Result<Self> {
+        Self::with_options(
+            url,
+            auth,
+            ClientOptions {
+                identity,
+                trust_cache: Some(trust_cache),
+                ..Default::default()
+            },
+        )
+    }
+
+    /// Same as [`Self::with_identity`], but [`Self::attest`] additionally checks the quote's
+    /// platform measurements against `measurement_policy`, failing attestation (and leaving the
+    /// certificate unpinned) if they don't match.
+    pub fn with_measurement_policy(
+        url: &str,
+        auth: &AuthData,
+        identity: Option<ClientIdentity>,
+        measurement_policy: MeasurementPolicy,
+    ) -> Result<Self> {
+        Self::with_options(
+            url,
+            auth,
+            ClientOptions {
+                identity,
+                measurement_policy: Some(measurement_policy),
+                ..Default::default()
+            },
+        )
+    }
+
+    /// Same as [`Self::with_identity`], but [`Self::verify_quote_from_pccs`] resolves collateral
+    /// (TCB info, QE identity, CRLs, etc.) from `collateral_source` instead of always hitting
+    /// Intel's public PCCS -- for a custom/on-prem PCCS mirror, a cached lookup that avoids
+    /// re-fetching collateral for every attestation against the same platform, or a fully
+    /// offline bundle for air-gapped verification.
+    pub fn with_collateral_source(
+        url: &str,
+        auth: &AuthData,
+        identity: Option<ClientIdentity>,
+        collateral_source: CollateralSource,
+    ) -> Result<Self> {
+        Self::with_options(
+            url,
+            auth,
+            ClientOptions {
+                identity,
+                collateral_source,
+                ..Default::default()
+            },
+        )
+    }
+
+    /// Same as [`Self::with_identity`], but [`Self::attest`] additionally checks the quote's
+    /// evaluated [`TcbStatus`] against `tcb_policy`, failing attestation if it isn't accepted.
+    pub fn with_tcb_policy(
+        url: &str,
+        auth: &AuthData,
+        identity: Option<ClientIdentity>,
+        tcb_policy: TcbPolicy,
+    ) -> Result<Self> {
+        Self::with_options(
+            url,
+            auth,
+            ClientOptions {
+                identity,
+                tcb_policy: Some(tcb_policy),
+                ..Default::default()
+            },
+        )
+    }
+
+    fn with_options(url: &str, auth: &AuthData, options: ClientOptions) -> Result<Self> {
+        let ClientOptions {
+            identity,
+            trust_cache,
+            measurement_policy,
+            collateral_source,
+            tcb_policy,
+        } = options;
+
+        // Parsed to validate the URL up front and to derive the trust cache key below; the
+        // actual connection is made by `client`.
         let parsed_url = Url::parse(url).context("Failed to parse URL")?;
-        let hostname = parsed_url.host_str().unwrap_or("localhost").to_string();
-        let port = parsed_url
-            .port()
-            .unwrap_or(if parsed_url.scheme() == "https" {
-                443
-            } else {
-                80
-            })
-            .to_string();
+        let host = match (parsed_url.host_str(), parsed_url.port()) {
+            (Some(host), Some(port)) => format!("{}:{}", host, port),
+            (Some(host), None) => host.to_string(),
+            (None, _) => url.to_string(),
+        };
 
         // Create headers with auth if provided
         let mut headers = header::HeaderMap::new();
@@ -109,47 +1056,93 @@ impl CvmClient {
             header::HeaderValue::from_str(&auth_header).context("Failed to create auth header")?,
         );
 
-        // Create temporary file for certificate
-        let cert_file = NamedTempFile::new().context("Failed to create temp file")?;
-        let cert_path = cert_file.path().to_string_lossy().to_string();
-
-        // Keep the file handle alive by not dropping it
-        std::mem::forget(cert_file);
-
-        // Fetch server certificate
-        let cmd = format!(
-            "echo | openssl s_client -connect {}:{} -servername {} -showcerts 2>/dev/null </dev/null | openssl x509 -outform PEM > {}",
-            hostname, port, hostname, cert_path
-        );
-
-        let status = Command::new("sh")
-            .arg("-c")
-            .arg(&cmd)
-            .status()
-            .context("Failed to execute openssl command")?;
+        let verifier = Arc::new(PinningCertVerifier::new());
 
-        if !status.success() {
-            return Err(anyhow::anyhow!("Failed to fetch server certificate"));
-        }
+        let tls_config_builder = rustls::ClientConfig::builder()
+            .dangerous()
+            .with_custom_certificate_verifier(verifier.clone());
 
-        tracing::info!("Certificate saved to {}", cert_path);
+        let tls_config = match identity {
+            Some(identity) => {
+                let (cert_chain, key) = identity.into_rustls_parts()?;
+                tls_config_builder
+                    .with_client_auth_cert(cert_chain, key)
+                    .context("Failed to set up client TLS identity")?
+            }
+            None => tls_config_builder.with_no_client_auth(),
+        };
 
-        // Create HTTP client
         let client = Client::builder()
-            .danger_accept_invalid_certs(true) // We'll handle verification ourselves
+            .use_preconfigured_tls(tls_config)
             .build()
             .context("Failed to build HTTP client")?;
 
+        let trust_cache = trust_cache.map(TrustCache::new);
+
+        let mut is_attested = false;
+        if let Some(cache) = &trust_cache {
+            if let Some(entry) = cache.load(&host) {
+                match hex::decode(&entry.spki_hash) {
+                    Ok(bytes) if bytes.len() == 32 => {
+                        let mut spki_hash = SpkiHash::default();
+                        spki_hash.copy_from_slice(&bytes);
+                        verifier.pin(spki_hash);
+                        is_attested = true;
+                        tracing::info!("Loaded cached attestation trust for {}", host);
+                    }
+                    _ => tracing::warn!("Discarding malformed trust cache entry for {}", host),
+                }
+            }
+        }
+
         Ok(Self {
             url: url.to_string(),
+            host,
             headers,
-            is_attested: false,
-            cert_path,
+            is_attested,
+            verifier,
             client,
+            trust_cache,
+            measurement_policy,
+            collateral_source,
+            tcb_policy,
+            last_measurements: Mutex::new(None),
+            last_tcb_evaluation: Mutex::new(None),
         })
     }
 
-    /// Make an HTTP request with proper certificate verification
+    /// Deletes this client's trust cache entry, if any, so the next attestation starts fresh
+    /// instead of trusting a previously cached pin.
+    pub fn clear_trust_cache(&self) {
+        if let Some(cache) = &self.trust_cache {
+            cache.clear(&self.host);
+        }
+    }
+
+    /// The platform measurements and TCB status parsed from the most recent successful
+    /// [`Self::attest`], for callers that want to log or audit what was attested rather than
+    /// only getting a boolean success. `None` until the first successful attestation.
+    pub fn last_measurements(&self) -> Option<QuoteMeasurements> {
+        self.last_measurements
+            .lock()
+            .expect("CvmClient measurements mutex poisoned")
+            .clone()
+    }
+
+    /// The [`TcbEvaluation`] -- status and applicable advisory IDs -- from the most recent
+    /// successful [`Self::attest`]. `None` until the first successful attestation.
+    pub fn last_tcb_evaluation(&self) -> Option<TcbEvaluation> {
+        self.last_tcb_evaluation
+            .lock()
+            .expect("CvmClient TCB evaluation mutex poisoned")
+            .clone()
+    }
+
+    /// Make an HTTP request with proper certificate verification. On a `401`/`403` response, or
+    /// on a transport error -- most often [`PinningCertVerifier`] rejecting a handshake whose
+    /// leaf SPKI no longer matches the pinned one -- the CVM's certificate or quote may have
+    /// rotated since the last successful [`Self::attest`], so this clears `is_attested`,
+    /// re-attests once, and retries the original request exactly once before giving up.
     async fn make_request(
         &mut self,
         method: &str,
@@ -164,6 +1157,33 @@ impl CvmClient {
             Pin::from(Box::new(attest_future)).await?;
         }
 
+        match self.send_once(method, path, body.clone()).await {
+            Ok(response) => Ok(response),
+            Err(err) if path != "quote" && Self::should_reattest(&err) => {
+                tracing::warn!(
+                    "Request to {} failed ({}), re-attesting and retrying once",
+                    path,
+                    err
+                );
+                self.is_attested = false;
+                // Drop the stale pin so the re-attestation handshake can capture the new leaf.
+                *self
+                    .verifier
+                    .pinned
+                    .lock()
+                    .expect("PinningCertVerifier mutex poisoned") = None;
+                // Use Box::pin for the same reason as the initial attestation above.
+                let attest_future = self.attest();
+                Pin::from(Box::new(attest_future)).await?;
+                self.send_once(method, path, body).await
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Sends one request attempt, returning [`StatusError`] (carrying the response body) for a
+    /// non-2xx status rather than only the status code.
+    async fn send_once(&self, method: &str, path: &str, body: Option<String>) -> Result<String> {
         let url = format!("{}/{}", self.url, path.trim_start_matches('/'));
 
         let request_builder = match method.to_uppercase().as_str() {
@@ -196,20 +1216,29 @@ impl CvmClient {
         // Check status
         let status = response.status();
         if !status.is_success() {
-            let error_text = response.text().await.unwrap_or_default();
-            return Err(anyhow::anyhow!(
-                "Request failed with status {}: {}",
-                status,
-                error_text
-            ));
+            let body = response.text().await.unwrap_or_default();
+            return Err(StatusError { status, body }.into());
         }
 
         // Return response body
-        let body = response
+        response
             .text()
             .await
-            .context("Failed to read response body")?;
-        Ok(body)
+            .context("Failed to read response body")
+    }
+
+    /// Whether `err` looks like the CVM rotated its TLS certificate or let its attestation go
+    /// stale -- a `401`/`403` [`StatusError`], or a transport error (how a handshake rejected by
+    /// [`PinningCertVerifier`]'s pin mismatch surfaces) -- rather than an ordinary failure that
+    /// re-attesting wouldn't fix.
+    fn should_reattest(err: &anyhow::Error) -> bool {
+        match err.downcast_ref::<StatusError>() {
+            Some(status_err) => matches!(
+                status_err.status,
+                reqwest::StatusCode::UNAUTHORIZED | reqwest::StatusCode::FORBIDDEN
+            ),
+            None => err.downcast_ref::<reqwest::Error>().is_some(),
+        }
     }
 
     /// Perform attestation
@@ -226,52 +1255,78 @@ impl CvmClient {
         let expected_report_data = self.get_certificate_hash()?;
 
         // Verify the quote
-        self.verify_quote_and_report_data(&quote_response, &expected_report_data)
+        let verified_report = self
+            .verify_quote_and_report_data(&quote_response, &expected_report_data)
             .await?;
 
-        tracing::info!("Attestation successful - certificate is now trusted");
+        let measurements = QuoteMeasurements::from_verified_report(&verified_report)?;
+        if let Some(policy) = &self.measurement_policy {
+            policy
+                .check(&measurements)
+                .context("Quote failed measurement policy check")?;
+        }
+        *self
+            .last_measurements
+            .lock()
+            .expect("CvmClient measurements mutex poisoned") = Some(measurements);
+
+        let tcb_evaluation = TcbEvaluation::from_verified_report(&verified_report);
+        if let Some(policy) = &self.tcb_policy {
+            policy
+                .check(&tcb_evaluation)
+                .context("Quote failed TCB policy check")?;
+        }
+        *self
+            .last_tcb_evaluation
+            .lock()
+            .expect("CvmClient TCB evaluation mutex poisoned") = Some(tcb_evaluation);
+
+        // From here on, the verifier hard-fails any handshake whose leaf SPKI hash doesn't
+        // match this one -- a rotated certificate forces re-attestation rather than being
+        // silently trusted.
+        let spki_hash = self
+            .verifier
+            .captured_hash()
+            .ok_or_else(|| anyhow::anyhow!("No certificate was captured during attestation"))?;
+        self.verifier.pin(spki_hash);
+
+        tracing::info!("Attestation successful - certificate is now pinned");
         self.is_attested = true;
 
+        if let Some(cache) = &self.trust_cache {
+            let entry = TrustCacheEntry {
+                spki_hash: hex::encode(spki_hash),
+                report_data: hex::encode(expected_report_data),
+                attested_at_unix_secs: now_unix_secs(),
+            };
+            if let Err(e) = cache.store(&self.host, &entry) {
+                tracing::warn!("Failed to persist trust cache entry for {}: {}", self.host, e);
+            }
+        }
+
         Ok(quote_response)
     }
 
-    /// Get certificate hash and generate expected report data
+    /// Get the SPKI hash captured from the most recent handshake (see [`PinningCertVerifier`])
+    /// and generate the expected report data from it.
     pub fn get_certificate_hash(&self) -> Result<[u8; 64]> {
-        // Get certificate's public key hash
-        let cmd = format!(
-            "openssl x509 -in {} -pubkey -noout -outform DER | openssl dgst -sha256",
-            self.cert_path
-        );
-
-        let output = Command::new("sh")
-            .arg("-c")
-            .arg(&cmd)
-            .output()
-            .context("Failed to execute openssl command")?;
-
-        if !output.status.success() {
-            return Err(anyhow::anyhow!("Failed to get certificate public key hash"));
-        }
-
-        let ssl_pub_key = String::from_utf8_lossy(&output.stdout)
-            .split("= ")
-            .nth(1)
-            .ok_or_else(|| anyhow::anyhow!("Failed to parse public key hash"))?
-            .trim()
-            .to_string();
+        let spki_hash = self.verifier.captured_hash().ok_or_else(|| {
+            anyhow::anyhow!("No certificate captured yet; a request must be made first")
+        })?;
 
         // Generate report data for verification
-        let expected_report_data = generate_sha512_hash(&ssl_pub_key, "app-data");
+        let expected_report_data = generate_sha512_hash(&hex::encode(spki_hash), "app-data");
 
         Ok(expected_report_data)
     }
 
-    /// Verify a quote against expected report data
+    /// Verify a quote against expected report data, returning the [`VerifiedReport`] so the
+    /// caller (namely [`Self::attest`]) can go on to check its platform measurements.
     pub async fn verify_quote_and_report_data(
         &self,
         quote_response: &QuoteResponse,
         expected_report_data: &[u8; 64],
-    ) -> Result<()> {
+    ) -> Result<VerifiedReport> {
         // Extract and verify the report data
         let verified_report = self.verify_quote_from_pccs(quote_response).await?;
         let report_data = verified_report
@@ -288,7 +1343,7 @@ impl CvmClient {
             ));
         }
 
-        Ok(())
+        Ok(verified_report)
     }
 
     pub async fn verify_quote_from_pccs(
@@ -301,9 +1356,12 @@ impl CvmClient {
             hex::decode(&quote_response.quote).context("Failed to decode hex-encoded quote")?;
         tracing::info!("Verifying raw quote: {:?}", raw_quote);
 
-        let verified_report = get_collateral_and_verify(&raw_quote, None)
-            .await
-            .map_err(|e| anyhow::anyhow!("Failed to get quote collateral: {:?}", e))?;
+        let collateral = self.resolve_collateral(&raw_quote).await?;
+        let verified_report = verify_quote_with_collateral(
+            &raw_quote,
+            &collateral,
+            std::time::SystemTime::now(),
+        )?;
 
         // Log the verification result
         tracing::debug!("Quote verified successfully: {:?}", verified_report);
@@ -311,6 +1369,126 @@ impl CvmClient {
         Ok(verified_report)
     }
 
+    /// Resolves the collateral needed to verify `raw_quote`, per `self.collateral_source`: a
+    /// PCCS fetch (through `self.collateral_source`'s cache, if configured) or a pre-fetched
+    /// on-disk bundle for offline verification.
+    async fn resolve_collateral(&self, raw_quote: &[u8]) -> Result<Collateral> {
+        match &self.collateral_source {
+            CollateralSource::Pccs { base_url, cache } => {
+                let cache = cache.clone().map(CollateralCache::new);
+                let cache_key = cache.as_ref().map(|_| CollateralCache::key_for(raw_quote));
+
+                if let (Some(cache), Some(key)) = (&cache, &cache_key) {
+                    if let Some(collateral) = cache.load(key) {
+                        tracing::debug!("Using cached collateral for quote (key {})", key);
+                        return Ok(collateral);
+                    }
+                }
+
+                let collateral =
+                    tokio::time::timeout(COLLATERAL_FETCH_TIMEOUT, fetch_collateral_from_pccs(raw_quote, base_url))
+                        .await
+                        .context("Timed out fetching collateral from PCCS")??;
+
+                if let (Some(cache), Some(key)) = (&cache, &cache_key) {
+                    if let Err(e) = cache.store(key, &collateral) {
+                        tracing::warn!("Failed to persist collateral cache entry for {}: {}", key, e);
+                    }
+                }
+
+                Ok(collateral)
+            }
+            CollateralSource::Bundle { path } => {
+                let contents = std::fs::read_to_string(path)
+                    .with_context(|| format!("Failed to read collateral bundle {}", path.display()))?;
+                serde_json::from_str(&contents)
+                    .with_context(|| format!("Failed to parse collateral bundle {}", path.display()))
+            }
+        }
+    }
+
+    /// Verifies each of `quotes` independently via [`Self::verify_quote_from_pccs`], groups the
+    /// ones that verify by their MRTD/RTMR measurement, and reports whether at least `threshold`
+    /// distinct nodes agree on an identical measurement -- the "M-of-N" acceptance rule a
+    /// deployment running the same workload across several replicated TDX nodes can require
+    /// before releasing a secret, mirroring a multisig quorum. When `expected_mrtd` is set, only
+    /// quotes reporting that exact MRTD are eligible to join the quorum.
+    ///
+    /// Note: two members that verify with the same PCK cert fingerprint (see
+    /// [`pck_leaf_cert_fingerprint`]) are the same physical platform and only the first is
+    /// counted toward `threshold` -- otherwise one node, or one party controlling several of
+    /// `quotes`, could resubmit the same quote (or several quotes from the same platform) and
+    /// satisfy an M-of-N quorum on its own.
+    pub async fn verify_quote_quorum(
+        &self,
+        quotes: &[QuoteResponse],
+        threshold: usize,
+        expected_mrtd: Option<[u8; 48]>,
+    ) -> QuorumResult {
+        // Each verification is an independent PCCS round trip, so run them concurrently instead
+        // of paying for N sequential collateral fetches.
+        let outcomes = futures::future::join_all(
+            quotes
+                .iter()
+                .map(|quote| self.verify_one_for_quorum(quote, expected_mrtd)),
+        )
+        .await;
+
+        tally_quorum_outcomes(outcomes, threshold)
+    }
+
+    /// Verifies one quote for [`Self::verify_quote_quorum`]: runs the usual
+    /// [`Self::verify_quote_from_pccs`] path, then applies `self.tcb_policy` and `expected_mrtd`
+    /// the same way [`Self::attest`] applies `self.tcb_policy` and `self.measurement_policy` --
+    /// a platform the client is configured to distrust doesn't get to count toward the quorum.
+    async fn verify_one_for_quorum(
+        &self,
+        quote: &QuoteResponse,
+        expected_mrtd: Option<[u8; 48]>,
+    ) -> QuorumMemberOutcome {
+        let verified_report = match self.verify_quote_from_pccs(quote).await {
+            Ok(verified_report) => verified_report,
+            Err(e) => return QuorumMemberOutcome::Failed(e.to_string()),
+        };
+
+        let measurements = match QuoteMeasurements::from_verified_report(&verified_report) {
+            Ok(measurements) => measurements,
+            Err(e) => return QuorumMemberOutcome::Failed(e.to_string()),
+        };
+
+        if let Some(policy) = &self.tcb_policy {
+            let evaluation = TcbEvaluation::from_verified_report(&verified_report);
+            if let Err(e) = policy.check(&evaluation) {
+                return QuorumMemberOutcome::Failed(e.to_string());
+            }
+        }
+
+        if let Some(expected) = expected_mrtd {
+            if expected != measurements.mr_td {
+                return QuorumMemberOutcome::Failed(format!(
+                    "mr_td {} doesn't match expected {}",
+                    hex::encode(measurements.mr_td),
+                    hex::encode(expected)
+                ));
+            }
+        }
+
+        let pck_cert_fingerprint = hex::decode(&quote.quote)
+            .ok()
+            .and_then(|raw_quote| pck_leaf_cert_fingerprint(&raw_quote));
+        if pck_cert_fingerprint.is_none() {
+            tracing::warn!(
+                "Could not extract a PCK cert fingerprint from quote; it will count toward the \
+                 quorum as its own platform regardless of whether it duplicates another member"
+            );
+        }
+
+        QuorumMemberOutcome::Verified {
+            measurements,
+            pck_cert_fingerprint,
+        }
+    }
+
     /// Get quote from server
     pub async fn get_quote(&mut self) -> Result<QuoteResponse> {
         let response = self.make_request("GET", "quote", None).await?;
@@ -361,9 +1539,19 @@ pub fn generate_sha512_hash(report_data: &str, prefix: &str) -> [u8; 64] {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::fs::File;
-    use std::io::Write;
-    use std::path::PathBuf;
+
+    fn test_auth() -> AuthData {
+        AuthData {
+            account_id: "test.near".to_string(),
+            public_key: "ed25519:11111111111111111111111111111111".to_string(),
+            signature: String::new(),
+            message: String::new(),
+            nonce: String::new(),
+            recipient: String::new(),
+            callback_url: None,
+            on_behalf_of: None,
+        }
+    }
 
     #[test]
     fn test_generate_sha512_hash() {
@@ -374,57 +1562,41 @@ mod tests {
     }
 
     #[test]
-    fn test_get_certificate_hash() {
-        // Create a temporary certificate file
-        let cert_path_str = PathBuf::from("/tmp/test_cert.pem")
-            .to_string_lossy()
-            .to_string();
-
-        // Write a dummy certificate to the file
-        let dummy_cert = r#"-----BEGIN CERTIFICATE-----
-MIIDazCCAlOgAwIBAgIUJFdU6o9MnCCDdmWAGYR2RMjJfMowDQYJKoZIhvcNAQEL
-BQAwRTELMAkGA1UEBhMCQVUxEzARBgNVBAgMClNvbWUtU3RhdGUxITAfBgNVBAoM
-GEludGVybmV0IFdpZGdpdHMgUHR5IEx0ZDAeFw0yMzA0MTIxMzI2MThaFw0yNDA0
-MTExMzI2MThaMEUxCzAJBgNVBAYTAkFVMRMwEQYDVQQIDApTb21lLVN0YXRlMSEw
-HwYDVQQKDBhJbnRlcm5ldCBXaWRnaXRzIFB0eSBMdGQwggEiMA0GCSqGSIb3DQEB
-AQUAA4IBDwAwggEKAoIBAQDCpLmrXQXLAN0zr8VMCvM0ImO2r8Gg3JKLdZDEZVKg
-BIjY0mN3HwM0y2QkA6hYZ3QnMa3IqJmYzLtA+jR+GJqRIzYMuLWm9AECJXkRiJ6A
-RBSp8h0LRZVMhC0U3pdwqdY/XvQQA3T0IBGD/5tZ+GjZQGYCnHV1iMjgp/nWo+Zv
-Qv3CKvYpg4g/V3LZ+UjmDmrVdVrJGfuXNHCKGVJKGGJpne0xtDPfkxiPZXkK9tXx
-JZwHSi0Na4JQDwlWLDM0qJv3Ql/kYKX+eLvNvGG8ysK1B5zKKQk9KlZJUTQKIQCw
-+MBVbVL9y7EIgCXCiW3/nHh8gEKOL6L0a5zKRAGXAgMBAAGjUzBRMB0GA1UdDgQW
-BBQVJqRlzepVh5/1Rt/0bzBXvQKnXDAfBgNVHSMEGDAWgBQVJqRlzepVh5/1Rt/0
-bzBXvQKnXDAPBgNVHRMBAf8EBTADAQH/MA0GCSqGSIb3DQEBCwUAA4IBAQBxC7QK
-FBUHxN9+MjdRc5MaJFB+9TUjHRNqItMCXwqRGUuGhkY/UXlFkYr9ij5uTJzDLDHb
-eYm5UzVPZsQUVRCFG+lTrLZhwu4YWIjQJsYKNUQXzGGDPvgG1vTFP2OCrYF9JTBX
-Hn3M9Xyj/XHnDQMOUj8X9jnLXAUcNVh/vKj0E/QvW7yRBXl5Qk+RpFGrL5iYX5/O
-BaXHvdEpRyizMdU7RQIqBbZUzEqDgPnGJjNYXxrElzxHQKcQb5Lh1jWx3fGLmgwW
-Cq1LkE+vQGFzk/ZkiP9EvP2FnYMGfZnRJIzGLxm2jRqR9GXB/MJXpRQnQVBDyMJO
-UXdRQJsvyCFJzLEA
------END CERTIFICATE-----"#;
-
-        let mut file = File::create(&cert_path_str).unwrap();
-        file.write_all(dummy_cert.as_bytes()).unwrap();
-
-        // Create a client with the certificate
-        let client = CvmClient {
-            url: "https://example.com".to_string(),
-            headers: header::HeaderMap::new(),
-            is_attested: false,
-            cert_path: cert_path_str.clone(),
-            client: Client::new(),
-        };
-
-        // Get the certificate hash
-        let result = client.get_certificate_hash();
-
-        // Verify the result
-        assert!(result.is_ok());
-        let hash = result.unwrap();
-        assert_eq!(hash.len(), 64);
+    fn test_get_certificate_hash_uses_captured_spki() {
+        let client = CvmClient::new("https://example.com", &test_auth()).unwrap();
+
+        // No handshake has happened yet, so nothing has been captured.
+        assert!(client.get_certificate_hash().is_err());
+
+        // Simulate a handshake capturing the leaf certificate's SPKI hash.
+        let spki_hash: SpkiHash = [7u8; 32];
+        *client
+            .verifier
+            .captured
+            .lock()
+            .expect("PinningCertVerifier mutex poisoned") = Some(spki_hash);
+
+        let result = client.get_certificate_hash().unwrap();
+        let expected = generate_sha512_hash(&hex::encode(spki_hash), "app-data");
+        assert_eq!(result, expected);
+    }
 
-        // Clean up
-        std::fs::remove_file(&cert_path_str).unwrap_or_default();
+    #[test]
+    fn test_pin_records_expected_spki_hash() {
+        let verifier = PinningCertVerifier::new();
+        verifier.pin([1u8; 32]);
+        assert_eq!(verifier.captured_hash(), None);
+
+        // `verify_server_cert` itself needs a real DER certificate to parse, which this test
+        // doesn't synthesize; this only exercises that pinning records the expected hash for it
+        // to compare against.
+        assert_eq!(
+            *verifier
+                .pinned
+                .lock()
+                .expect("PinningCertVerifier mutex poisoned"),
+            Some([1u8; 32])
+        );
     }
 
     #[tokio::test]
@@ -432,13 +1604,7 @@ UXdRQJsvyCFJzLEA
         let _ = tracing_subscriber::fmt::try_init();
 
         // Create a test client
-        let client = CvmClient {
-            url: "https://example.com".to_string(),
-            headers: header::HeaderMap::new(),
-            is_attested: false,
-            cert_path: "/tmp/test_cert.pem".to_string(),
-            client: Client::new(),
-        };
+        let client = CvmClient::new("https://example.com", &test_auth()).unwrap();
 
         // Create the quote response
         let quote_response = QuoteResponse {
@@ -451,4 +1617,239 @@ UXdRQJsvyCFJzLEA
             .await
             .unwrap();
     }
+
+    /// A fresh, process-unique scratch directory under the OS temp dir, for tests that exercise
+    /// on-disk caches without stepping on each other or leaving state behind.
+    fn scratch_dir(label: &str) -> std::path::PathBuf {
+        use std::sync::atomic::{AtomicU64, Ordering};
+        static COUNTER: AtomicU64 = AtomicU64::new(0);
+        let n = COUNTER.fetch_add(1, Ordering::Relaxed);
+        std::env::temp_dir().join(format!("cvm_client_test_{}_{}_{}", std::process::id(), label, n))
+    }
+
+    fn measurements_with_mrtd(mrtd: u8) -> QuoteMeasurements {
+        QuoteMeasurements {
+            mr_td: [mrtd; 48],
+            rtmr0: [0; 48],
+            rtmr1: [0; 48],
+            rtmr2: [0; 48],
+            rtmr3: [0; 48],
+            mr_config_id: [0; 48],
+            mr_owner: [0; 48],
+            tcb_status: "UpToDate".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_trust_cache_load_honors_ttl() {
+        let dir = scratch_dir("trust_ttl");
+        let cache = TrustCache::new(TrustCacheConfig {
+            dir,
+            ttl: Duration::from_secs(3600),
+        });
+        let entry = TrustCacheEntry {
+            spki_hash: hex::encode([9u8; 32]),
+            report_data: hex::encode([1u8; 64]),
+            attested_at_unix_secs: now_unix_secs(),
+        };
+        cache.store("host:1234", &entry).unwrap();
+
+        let loaded = cache.load("host:1234").expect("fresh entry should load");
+        assert_eq!(loaded.spki_hash, entry.spki_hash);
+
+        cache.clear("host:1234");
+        std::fs::remove_dir_all(&cache.dir).ok();
+    }
+
+    #[test]
+    fn test_trust_cache_load_expires_past_ttl() {
+        let dir = scratch_dir("trust_expiry");
+        let cache = TrustCache::new(TrustCacheConfig {
+            dir,
+            ttl: Duration::from_secs(60),
+        });
+        let entry = TrustCacheEntry {
+            spki_hash: hex::encode([9u8; 32]),
+            report_data: hex::encode([1u8; 64]),
+            attested_at_unix_secs: now_unix_secs().saturating_sub(3600),
+        };
+        cache.store("host:1234", &entry).unwrap();
+
+        assert!(cache.load("host:1234").is_none());
+        std::fs::remove_dir_all(&cache.dir).ok();
+    }
+
+    #[test]
+    fn test_trust_cache_load_survives_malformed_entry() {
+        let dir = scratch_dir("trust_malformed");
+        std::fs::create_dir_all(&dir).unwrap();
+        let cache = TrustCache::new(TrustCacheConfig {
+            dir: dir.clone(),
+            ttl: Duration::from_secs(3600),
+        });
+        std::fs::write(cache.path_for("host:1234"), b"not json").unwrap();
+
+        assert!(cache.load("host:1234").is_none());
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_collateral_cache_round_trip() {
+        let dir = scratch_dir("collateral");
+        let cache = CollateralCache::new(CollateralCacheConfig {
+            dir: dir.clone(),
+            refresh_interval: Duration::from_secs(3600),
+        });
+        let raw_quote = b"some raw quote bytes";
+        let key = CollateralCache::key_for(raw_quote);
+        let collateral = Collateral {
+            pck_crl_issuer_chain: "chain".to_string(),
+            root_ca_crl: "root crl".to_string(),
+            pck_crl: "pck crl".to_string(),
+            tcb_info_issuer_chain: "tcb chain".to_string(),
+            tcb_info: "{}".to_string(),
+            tcb_info_signature: vec![1, 2, 3],
+            qe_identity_issuer_chain: "qe chain".to_string(),
+            qe_identity: "{}".to_string(),
+            qe_identity_signature: vec![4, 5, 6],
+        };
+
+        assert!(cache.load(&key).is_none());
+        cache.store(&key, &collateral).unwrap();
+        let loaded = cache.load(&key).expect("just-stored entry should load");
+        assert_eq!(loaded.root_ca_crl, collateral.root_ca_crl);
+        assert_eq!(loaded.pck_crl, collateral.pck_crl);
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_tcb_policy_check_accept_and_reject() {
+        let policy = TcbPolicy::accepting(vec![TcbStatus::UpToDate, TcbStatus::ConfigurationNeeded]);
+
+        let ok = TcbEvaluation {
+            status: TcbStatus::ConfigurationNeeded,
+            advisory_ids: vec![],
+        };
+        assert!(policy.check(&ok).is_ok());
+
+        let rejected = TcbEvaluation {
+            status: TcbStatus::OutOfDate,
+            advisory_ids: vec!["INTEL-SA-1234".to_string()],
+        };
+        let err = policy.check(&rejected).unwrap_err();
+        assert_eq!(err.status, TcbStatus::OutOfDate);
+        assert_eq!(err.advisory_ids, vec!["INTEL-SA-1234".to_string()]);
+    }
+
+    #[test]
+    fn test_tcb_policy_strict_only_accepts_up_to_date() {
+        let policy = TcbPolicy::strict();
+        assert!(policy
+            .check(&TcbEvaluation {
+                status: TcbStatus::UpToDate,
+                advisory_ids: vec![],
+            })
+            .is_ok());
+        assert!(policy
+            .check(&TcbEvaluation {
+                status: TcbStatus::SwHardeningNeeded,
+                advisory_ids: vec![],
+            })
+            .is_err());
+    }
+
+    #[test]
+    fn test_should_reattest_classifies_auth_failures_and_other_errors() {
+        let unauthorized = anyhow::Error::new(StatusError {
+            status: reqwest::StatusCode::UNAUTHORIZED,
+            body: String::new(),
+        });
+        assert!(CvmClient::should_reattest(&unauthorized));
+
+        let forbidden = anyhow::Error::new(StatusError {
+            status: reqwest::StatusCode::FORBIDDEN,
+            body: String::new(),
+        });
+        assert!(CvmClient::should_reattest(&forbidden));
+
+        let not_found = anyhow::Error::new(StatusError {
+            status: reqwest::StatusCode::NOT_FOUND,
+            body: String::new(),
+        });
+        assert!(!CvmClient::should_reattest(&not_found));
+
+        let other = anyhow::anyhow!("some unrelated failure");
+        assert!(!CvmClient::should_reattest(&other));
+    }
+
+    #[test]
+    fn test_tally_quorum_outcomes_satisfied_at_threshold() {
+        let outcomes = vec![
+            QuorumMemberOutcome::Verified {
+                measurements: measurements_with_mrtd(1),
+                pck_cert_fingerprint: Some([1u8; 32]),
+            },
+            QuorumMemberOutcome::Verified {
+                measurements: measurements_with_mrtd(1),
+                pck_cert_fingerprint: Some([2u8; 32]),
+            },
+            QuorumMemberOutcome::Failed("bad signature".to_string()),
+        ];
+
+        let result = tally_quorum_outcomes(outcomes, 2);
+        assert!(result.satisfied);
+        assert_eq!(result.winning_measurement.unwrap().mr_td, [1u8; 48]);
+        assert_eq!(result.members.len(), 3);
+    }
+
+    #[test]
+    fn test_tally_quorum_outcomes_dedupes_same_platform() {
+        // Same fingerprint submitted twice must not single-handedly satisfy a threshold of 2.
+        let outcomes = vec![
+            QuorumMemberOutcome::Verified {
+                measurements: measurements_with_mrtd(1),
+                pck_cert_fingerprint: Some([1u8; 32]),
+            },
+            QuorumMemberOutcome::Verified {
+                measurements: measurements_with_mrtd(1),
+                pck_cert_fingerprint: Some([1u8; 32]),
+            },
+        ];
+
+        let result = tally_quorum_outcomes(outcomes, 2);
+        assert!(!result.satisfied);
+        assert!(result.winning_measurement.is_none());
+    }
+
+    #[test]
+    fn test_tally_quorum_outcomes_below_threshold_not_satisfied() {
+        let outcomes = vec![QuorumMemberOutcome::Verified {
+            measurements: measurements_with_mrtd(1),
+            pck_cert_fingerprint: Some([1u8; 32]),
+        }];
+
+        let result = tally_quorum_outcomes(outcomes, 2);
+        assert!(!result.satisfied);
+    }
+
+    #[test]
+    fn test_tally_quorum_outcomes_ties_break_deterministically() {
+        // Two equally-sized, genuinely disagreeing groups (1 member each) at threshold 1: the
+        // group whose first member has the lower index must win, on every call.
+        let outcomes = vec![
+            QuorumMemberOutcome::Verified {
+                measurements: measurements_with_mrtd(2),
+                pck_cert_fingerprint: Some([2u8; 32]),
+            },
+            QuorumMemberOutcome::Verified {
+                measurements: measurements_with_mrtd(1),
+                pck_cert_fingerprint: Some([1u8; 32]),
+            },
+        ];
+
+        let result = tally_quorum_outcomes(outcomes, 1);
+        assert!(result.satisfied);
+        assert_eq!(result.winning_measurement.unwrap().mr_td, [2u8; 48]);
+    }
 }
@@ -0,0 +1,310 @@
+//! Parses a `docker-compose.yaml`-style file describing the CVM runner service, so operators
+//! can change the runner image, volumes, environment, and socket binds declaratively instead of
+//! recompiling this crate to edit [`crate::Manager`]'s hardcoded `add_cvm_to_pool` setup.
+//!
+//! Only the subset of the compose spec `Manager` actually needs is modeled here; any other
+//! top-level or service keys in the file are ignored rather than rejected.
+
+use crate::ContainerResources;
+use anyhow::{Context, Result};
+use bollard::container::Config;
+use bollard::models::{
+    HostConfig, HostConfigCgroupnsModeEnum, PortBinding, RestartPolicy, RestartPolicyNameEnum,
+};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Top-level `docker-compose.yaml` document.
+#[derive(Debug, Clone, Deserialize)]
+pub struct DockerCompose {
+    pub services: HashMap<String, Service>,
+    /// Named volumes declared under `volumes:`. A service's `volumes:` entries may reference
+    /// these by name instead of a host path; see [`DockerCompose::resolve_volumes`] for how a
+    /// `local`-driver, bind-mounted one gets resolved back to its host path.
+    #[serde(default)]
+    pub volumes: HashMap<String, Volume>,
+}
+
+/// One service under `services:`, e.g. the CVM runner.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Service {
+    pub image: String,
+    #[serde(default)]
+    pub environment: Vec<String>,
+    /// `host:container` bind mounts or `name:container` named-volume mounts, exactly as they'd
+    /// appear in a compose file (and as bollard's `HostConfig::binds` expects them).
+    #[serde(default)]
+    pub volumes: Vec<String>,
+    /// `"HOST:CONTAINER"` to bind a specific host port, or a bare `"CONTAINER"` to let Docker
+    /// assign one, e.g. `"443"` for the CVM API.
+    #[serde(default)]
+    pub ports: Vec<String>,
+    #[serde(default)]
+    pub privileged: bool,
+    /// Compose's `restart:` policy (`"no"`, `"always"`, `"on-failure"`, `"unless-stopped"`).
+    /// Unset leaves Docker's own default (`no`) in place.
+    pub restart: Option<String>,
+}
+
+/// A `volumes:` top-level entry. Only the `local` driver with bind-mount options is modeled --
+/// anything else is left to Docker to create as an anonymous named volume the first time it's
+/// used, same as before this was resolved at all.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Volume {
+    pub driver: Option<String>,
+    #[serde(default)]
+    pub driver_opts: HashMap<String, String>,
+}
+
+impl Volume {
+    /// If this is a `local`-driver bind mount (`driver_opts: {type: none, o: bind, device:
+    /// <path>}`), the host path it binds to `Manager`'s containers -- the same three keys
+    /// `docker volume create --driver local -o type=none -o o=bind -o device=<path>` sets.
+    fn bind_device(&self) -> Option<&str> {
+        if self.driver.as_deref().unwrap_or("local") != "local" {
+            return None;
+        }
+        if self.driver_opts.get("o").map(String::as_str) != Some("bind") {
+            return None;
+        }
+        self.driver_opts.get("device").map(String::as_str)
+    }
+}
+
+impl DockerCompose {
+    /// Reads and parses a compose file from `path`.
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read compose file {}", path.display()))?;
+        serde_yaml::from_str(&contents)
+            .with_context(|| format!("Failed to parse compose file {}", path.display()))
+    }
+
+    /// Looks up a named service, e.g. `"runner"`.
+    pub fn service(&self, name: &str) -> Result<&Service> {
+        self.services
+            .get(name)
+            .ok_or_else(|| anyhow::anyhow!("Compose file has no service named '{}'", name))
+    }
+
+    /// Resolves `service`'s `volumes:` entries against `self.volumes`, rewriting any reference
+    /// to a `local`-driver bind-mounted named volume into a literal `host:container[:mode]`
+    /// bind -- the form bollard's `HostConfig::binds` expects. Entries that aren't a named-volume
+    /// reference (already a host path) or don't resolve to a bind mount pass through unchanged.
+    /// Called by [`crate::Manager::from_compose`] to resolve a service's volumes once, before
+    /// storing it, rather than on every `to_container_config` call.
+    pub(crate) fn resolve_volumes(&self, service: &Service) -> Vec<String> {
+        service
+            .volumes
+            .iter()
+            .map(|entry| {
+                let mut parts = entry.splitn(3, ':');
+                let Some(source) = parts.next() else {
+                    return entry.clone();
+                };
+                let Some(target) = parts.next() else {
+                    return entry.clone();
+                };
+                let mode = parts.next();
+
+                let Some(device) = self.volumes.get(source).and_then(Volume::bind_device) else {
+                    return entry.clone();
+                };
+
+                match mode {
+                    Some(mode) => format!("{}:{}:{}", device, target, mode),
+                    None => format!("{}:{}", device, target),
+                }
+            })
+            .collect()
+    }
+}
+
+impl Service {
+    /// Translates this service, plus the [`ContainerResources`] limits the compose format
+    /// doesn't express, into the bollard [`Config`] `Manager` passes to `create_container`.
+    /// `self.volumes` is used as-is, so a `Service` obtained via [`Manager::from_compose`] (which
+    /// resolves named-volume bind mounts first, see [`DockerCompose::resolve_volumes`]) should be
+    /// passed in rather than a freshly parsed one when that resolution matters.
+    ///
+    /// [`Manager::from_compose`]: crate::Manager::from_compose
+    pub fn to_container_config(&self, resources: &ContainerResources) -> Config<String> {
+        let mut exposed_ports = HashMap::new();
+        let mut port_bindings = HashMap::new();
+        for port in &self.ports {
+            // "HOST:CONTAINER" binds a specific host port; a bare "CONTAINER" lets Docker
+            // assign one at random.
+            let (host_port, container_port) = match port.split_once(':') {
+                Some((host, container)) => (Some(host.to_string()), container),
+                None => (None, port.as_str()),
+            };
+            let key = format!("{}/tcp", container_port);
+            exposed_ports.insert(key.clone(), HashMap::new());
+            port_bindings.insert(
+                key,
+                Some(vec![PortBinding {
+                    host_ip: Some("0.0.0.0".to_string()),
+                    host_port,
+                }]),
+            );
+        }
+
+        let host_config = HostConfig {
+            binds: Some(self.volumes.clone()),
+            port_bindings: Some(port_bindings),
+            memory: resources.memory,
+            nano_cpus: resources.nano_cpus,
+            shm_size: resources.shm_size,
+            pids_limit: resources.pids_limit,
+            privileged: Some(self.privileged || resources.privileged),
+            cgroupns_mode: resources
+                .cgroupns_private
+                .then_some(HostConfigCgroupnsModeEnum::PRIVATE),
+            userns_mode: resources.userns_mode.clone(),
+            restart_policy: self.restart.as_deref().map(restart_policy),
+            ..Default::default()
+        };
+
+        Config {
+            image: Some(self.image.clone()),
+            env: (!self.environment.is_empty()).then(|| self.environment.clone()),
+            exposed_ports: Some(exposed_ports),
+            host_config: Some(host_config),
+            ..Default::default()
+        }
+    }
+}
+
+/// Maps a compose `restart:` value to bollard's `RestartPolicy`. An unrecognized value is
+/// treated as `no`, same as Docker does for an invalid policy name.
+fn restart_policy(restart: &str) -> RestartPolicy {
+    let name = match restart {
+        "always" => RestartPolicyNameEnum::ALWAYS,
+        "on-failure" => RestartPolicyNameEnum::ON_FAILURE,
+        "unless-stopped" => RestartPolicyNameEnum::UNLESS_STOPPED,
+        _ => RestartPolicyNameEnum::NO,
+    };
+    RestartPolicy {
+        name: Some(name),
+        maximum_retry_count: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_runner_service() {
+        let yaml = r#"
+services:
+  runner:
+    image: plgnai/nearai_cvm_runner:latest
+    privileged: false
+    environment:
+      - RUST_LOG=info
+    ports:
+      - "443"
+    volumes:
+      - /var/run/tappd.sock:/var/run/tappd.sock
+volumes: {}
+"#;
+        let compose: DockerCompose = serde_yaml::from_str(yaml).unwrap();
+        let service = compose.service("runner").unwrap();
+        assert_eq!(service.image, "plgnai/nearai_cvm_runner:latest");
+        assert_eq!(service.environment, vec!["RUST_LOG=info".to_string()]);
+        assert_eq!(
+            service.volumes,
+            vec!["/var/run/tappd.sock:/var/run/tappd.sock".to_string()]
+        );
+
+        let config = service.to_container_config(&ContainerResources::default());
+        assert_eq!(config.image.as_deref(), Some("plgnai/nearai_cvm_runner:latest"));
+        assert!(
+            config
+                .exposed_ports
+                .as_ref()
+                .unwrap()
+                .contains_key("443/tcp")
+        );
+    }
+
+    #[test]
+    fn missing_service_is_an_error() {
+        let compose = DockerCompose {
+            services: HashMap::new(),
+            volumes: HashMap::new(),
+        };
+        assert!(compose.service("runner").is_err());
+    }
+
+    #[test]
+    fn resolves_local_bind_named_volume() {
+        let yaml = r#"
+services:
+  runner:
+    image: plgnai/nearai_cvm_runner:latest
+    restart: unless-stopped
+    ports:
+      - "8443:443"
+    volumes:
+      - tappd-data:/var/run/tappd.sock:ro
+volumes:
+  tappd-data:
+    driver: local
+    driver_opts:
+      type: none
+      o: bind
+      device: /var/run/tappd.sock
+"#;
+        let compose: DockerCompose = serde_yaml::from_str(yaml).unwrap();
+        let service = compose.service("runner").unwrap();
+
+        let resolved = compose.resolve_volumes(service);
+        assert_eq!(
+            resolved,
+            vec!["/var/run/tappd.sock:/var/run/tappd.sock:ro".to_string()]
+        );
+
+        let mut resolved_service = service.clone();
+        resolved_service.volumes = resolved;
+        let config = resolved_service.to_container_config(&ContainerResources::default());
+
+        let port_bindings = config.host_config.as_ref().unwrap().port_bindings.as_ref().unwrap();
+        let binding = port_bindings.get("443/tcp").unwrap().as_ref().unwrap();
+        assert_eq!(binding[0].host_port.as_deref(), Some("8443"));
+
+        assert!(matches!(
+            config
+                .host_config
+                .as_ref()
+                .unwrap()
+                .restart_policy
+                .as_ref()
+                .unwrap()
+                .name,
+            Some(RestartPolicyNameEnum::UNLESS_STOPPED)
+        ));
+    }
+
+    #[test]
+    fn unreferenced_volume_passes_through_unchanged() {
+        let compose = DockerCompose {
+            services: HashMap::new(),
+            volumes: HashMap::new(),
+        };
+        let service = Service {
+            image: "img".to_string(),
+            environment: vec![],
+            volumes: vec!["/host/path:/container/path".to_string()],
+            ports: vec![],
+            privileged: false,
+            restart: None,
+        };
+        assert_eq!(
+            compose.resolve_volumes(&service),
+            vec!["/host/path:/container/path".to_string()]
+        );
+    }
+}
@@ -1,10 +1,177 @@
 use anyhow::{Context, Result};
 use bollard::Docker;
-use bollard::container::{Config, CreateContainerOptions};
+use bollard::container::{
+    Config, CreateContainerOptions, LogsOptions, RemoveContainerOptions, StopContainerOptions,
+};
 use bollard::image::CreateImageOptions;
 use bollard::models::{HostConfig, PortBinding};
 use futures::StreamExt;
+use regex::Regex;
 use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use std::time::Duration;
+use thiserror::Error;
+use tokio::sync::Mutex;
+
+pub mod compose;
+
+/// How often the background reaper (see [`Manager::spawn_reaper`]) re-checks every pooled
+/// container's health.
+const REAP_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Number of consecutive failed attestation checks a pooled container is allowed before the
+/// reaper evicts it immediately, rather than leaving it to fail forever.
+const MAX_ATTESTATION_FAILURES: u32 = 3;
+
+/// Label key set on every container `launch_container` creates, so a restarted `Manager` can
+/// find its own leaked containers via `list_containers` (see [`Manager::reconcile_orphans`])
+/// without relying on the `cvm-*` name prefix alone.
+const MANAGED_BY_LABEL_KEY: &str = "managed-by";
+const MANAGED_BY_LABEL_VALUE: &str = "nearai-cvm";
+
+/// Number of trailing log lines pulled into an attestation-failure warning/error, enough to
+/// show the runner's startup failure without dumping its whole history.
+const ATTESTATION_FAILURE_LOG_TAIL: &str = "20";
+
+/// How a newly-started CVM container is polled to decide it's actually ready to receive
+/// traffic, rather than merely running. Image pulls can take minutes and must never count
+/// against this; only the poll after `start_container` is bounded by `startup_timeout` (or a
+/// variant's own `timeout`, when it carries one).
+#[derive(Debug, Clone)]
+pub enum WaitStrategy {
+    /// Poll the CVM's `/attest` endpoint until it responds successfully. The default: this is
+    /// the same check `get_cvm` relies on, so a container can't be handed out before it would
+    /// pass that check anyway.
+    AttestationReady,
+    /// Stream the container's logs (via bollard's `logs` API) until a line matches `pattern`,
+    /// bounded by `timeout` rather than the manager-wide `startup_timeout` -- useful when one
+    /// compose service's runner is known to boot slower than the rest of the pool.
+    LogLine { pattern: Regex, timeout: Duration },
+    /// Poll until the container's mapped host port accepts a TCP connection, bounded by
+    /// `timeout` rather than the manager-wide `startup_timeout`.
+    TcpPort { timeout: Duration },
+    /// Poll an HTTPS health-check endpoint on the container until it returns a 2xx status.
+    HealthCheck { path: String },
+    /// Skip readiness polling entirely and push the port into `free_cvm_ports` as soon as the
+    /// container starts. Only appropriate for images already known to bind immediately; prefer
+    /// one of the other variants otherwise.
+    None,
+}
+
+/// Default timeout for [`WaitStrategy`] polling after a container has started, chosen to
+/// comfortably cover a CVM runner's own boot and attestation setup without masking a genuinely
+/// broken container for too long.
+const DEFAULT_STARTUP_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// How often [`WaitStrategy`] is re-checked while waiting for a container to become ready.
+const READINESS_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Error)]
+pub enum ReadinessError {
+    #[error(
+        "container {container_id} (port {port}) did not become ready via {strategy:?} within {timeout:?}"
+    )]
+    StartupTimeout {
+        container_id: String,
+        port: u16,
+        strategy: WaitStrategy,
+        timeout: Duration,
+    },
+}
+
+/// Resource limits and isolation flags applied to every CVM runner container, so one
+/// misbehaving agent can't starve the host or the other CVMs sharing it.
+#[derive(Debug, Clone, Default)]
+pub struct ContainerResources {
+    /// Hard memory limit in bytes (Docker `--memory`). Unset means no limit.
+    pub memory: Option<i64>,
+    /// CPU quota in units of 10^-9 CPUs (Docker `--cpus`, e.g. `1_000_000_000` for one core).
+    /// Unset means no limit.
+    pub nano_cpus: Option<i64>,
+    /// Size of `/dev/shm` in bytes (Docker `--shm-size`). Unset uses the Docker default.
+    pub shm_size: Option<i64>,
+    /// Maximum number of processes/threads the container may create (Docker `--pids-limit`).
+    /// Unset means no limit.
+    pub pids_limit: Option<i64>,
+    /// Runs the container with extended (Docker `--privileged`) capabilities. Defaults to
+    /// `false`; should stay that way unless the runner image specifically needs it.
+    pub privileged: bool,
+    /// Isolates the container into its own cgroup namespace (Docker `--cgroupns=private`)
+    /// instead of sharing the host's.
+    pub cgroupns_private: bool,
+    /// User-namespace remapping mode (Docker `--userns`, e.g. `Some("host".into())`). Unset
+    /// uses the daemon-wide default.
+    pub userns_mode: Option<String>,
+}
+
+/// Tunables for a [`Manager`], configurable at construction time and otherwise defaulted.
+#[derive(Debug, Clone)]
+pub struct ManagerConfig {
+    pub wait_strategy: WaitStrategy,
+    pub startup_timeout: Duration,
+    pub container_resources: ContainerResources,
+    /// A service parsed from a `docker-compose.yaml` (see [`compose`]) describing the image,
+    /// env, volumes, and ports to use for every pool replica, in place of the hardcoded
+    /// single-image, single-bind setup.
+    pub compose_service: Option<compose::Service>,
+    /// How the pool grows beyond, and shrinks back toward, the `pool_size` passed to
+    /// [`Manager::new_with_config`]. Defaults to [`PoolSizing::fixed`] of that `pool_size`,
+    /// i.e. the pool neither grows nor shrinks unless this is set explicitly.
+    pub sizing: Option<PoolSizing>,
+}
+
+impl Default for ManagerConfig {
+    fn default() -> Self {
+        Self {
+            wait_strategy: WaitStrategy::AttestationReady,
+            startup_timeout: DEFAULT_STARTUP_TIMEOUT,
+            container_resources: ContainerResources::default(),
+            compose_service: None,
+            sizing: None,
+        }
+    }
+}
+
+/// How long a free container may sit idle in the pool before [`Manager`]'s background sizing
+/// sweep (see [`Manager::shrink_idle_pool`]) reclaims it, once the free count exceeds
+/// `min_size`.
+const DEFAULT_IDLE_TTL: Duration = Duration::from_secs(5 * 60);
+
+/// Governs how a [`Manager`]'s pool grows past, and shrinks back toward, its floor -- instead
+/// of staying at a fixed size for its whole lifetime.
+#[derive(Debug, Clone, Copy)]
+pub struct PoolSizing {
+    /// Floor: [`Manager::fill_cvm_pool`] always keeps at least this many containers warm, and
+    /// the shrink sweep never reaps free containers below it.
+    pub min_size: usize,
+    /// Ceiling: [`Manager::acquire`] refuses to spin up a new container once the pool's total
+    /// (free + in-use) size reaches this.
+    pub max_size: usize,
+    /// How long a container may sit idle in the free pool, once free count exceeds `min_size`,
+    /// before the shrink sweep stops and removes it.
+    pub idle_ttl: Duration,
+}
+
+impl PoolSizing {
+    /// A pool that neither grows nor shrinks: `min_size == max_size == size`, matching the
+    /// pre-[`PoolSizing`] behavior where `pool_size` was the whole story.
+    pub fn fixed(size: usize) -> Self {
+        Self {
+            min_size: size,
+            max_size: size,
+            idle_ttl: DEFAULT_IDLE_TTL,
+        }
+    }
+}
+
+/// Free/in-use/total occupancy of a [`Manager`]'s pool, as of the moment [`Manager::pool_metrics`]
+/// was called.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PoolMetrics {
+    pub free: usize,
+    pub in_use: usize,
+    pub total: usize,
+}
 
 pub struct RunConfig {
     provider: String,
@@ -35,18 +202,238 @@ impl RunConfig {
     }
 }
 
+/// A dedicated user-defined Docker network for one `Manager`'s pooled containers, so two
+/// `Manager`s (or two test runs) in the same daemon can't see each other's CVMs or collide on
+/// in-network ports. Shared via `Arc` -- the `Manager` holds one reference and every
+/// [`CvmContainerGuard`] holds another -- and removed only once the last reference drops.
+struct ManagedNetwork {
+    docker: Docker,
+    id: String,
+    name: String,
+    runtime: tokio::runtime::Handle,
+}
+
+/// Distinguishes networks created by concurrent `Manager`s within the same process (e.g.
+/// parallel tests), alongside the process id in the network name.
+static NETWORK_COUNTER: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(0);
+
+impl ManagedNetwork {
+    async fn create(docker: Docker) -> Result<Self> {
+        let name = format!(
+            "cvm-net-{}-{}",
+            std::process::id(),
+            NETWORK_COUNTER.fetch_add(1, std::sync::atomic::Ordering::Relaxed)
+        );
+
+        let response = docker
+            .create_network(bollard::network::CreateNetworkOptions {
+                name: name.as_str(),
+                driver: "bridge",
+                ..Default::default()
+            })
+            .await
+            .context("Failed to create CVM pool network")?;
+
+        let id = response
+            .id
+            .ok_or_else(|| anyhow::anyhow!("Docker did not return an id for network {}", name))?;
+
+        tracing::info!("Created CVM pool network {} ({})", name, id);
+
+        Ok(Self {
+            docker,
+            id,
+            name,
+            runtime: tokio::runtime::Handle::current(),
+        })
+    }
+}
+
+impl Drop for ManagedNetwork {
+    fn drop(&mut self) {
+        let docker = self.docker.clone();
+        let id = self.id.clone();
+        let name = self.name.clone();
+        self.runtime.spawn(async move {
+            if let Err(e) = docker.remove_network(&id).await {
+                tracing::warn!("Failed to remove CVM pool network {}: {}", name, e);
+            } else {
+                tracing::info!("Removed CVM pool network {}", name);
+            }
+        });
+    }
+}
+
+/// RAII guard around a just-created container that hasn't yet finished [`Manager::launch_container`]'s
+/// setup. If dropped still armed -- an early return via `?`, or a panic, anywhere between
+/// container creation and the end of setup -- it stops+removes the container so a failed
+/// inspect/readiness check can't leak it. `Drop` is synchronous, so cleanup is handed off to a
+/// spawned task on the runtime the guard was created on rather than run inline.
+struct CvmContainerGuard {
+    docker: Docker,
+    container_id: String,
+    armed: bool,
+    runtime: tokio::runtime::Handle,
+    /// Kept alive for the guard's lifetime so the container's network can't be torn down out
+    /// from under it mid-setup; dropped along with the guard either way.
+    _network: Arc<ManagedNetwork>,
+}
+
+impl CvmContainerGuard {
+    fn new(docker: Docker, container_id: String, network: Arc<ManagedNetwork>) -> Self {
+        Self {
+            docker,
+            container_id,
+            armed: true,
+            runtime: tokio::runtime::Handle::current(),
+            _network: network,
+        }
+    }
+
+    /// Cancels the cleanup: call once the container has been fully set up and handed off to
+    /// `active_containers`.
+    fn disarm(mut self) {
+        self.armed = false;
+    }
+}
+
+impl Drop for CvmContainerGuard {
+    fn drop(&mut self) {
+        if !self.armed {
+            return;
+        }
+
+        let docker = self.docker.clone();
+        let container_id = self.container_id.clone();
+        tracing::warn!(
+            "CvmContainerGuard for container {} dropped while still armed; cleaning it up",
+            container_id
+        );
+        self.runtime.spawn(async move {
+            let stop_options = StopContainerOptions { t: 10 };
+            let _ = docker.stop_container(&container_id, Some(stop_options)).await;
+            let remove_options = RemoveContainerOptions {
+                force: true,
+                ..Default::default()
+            };
+            let _ = docker.remove_container(&container_id, Some(remove_options)).await;
+        });
+    }
+}
+
+/// A CVM pool manager. Cheap to clone: the pool state is shared via `Arc<Mutex<_>>`, which is
+/// what lets [`Self::spawn_reaper`] hold its own handle into the same pool the original
+/// `Manager` hands out ports from.
+#[derive(Clone)]
 pub struct Manager {
     docker: Docker,
     runner_image_name: String,
-    free_cvm_ports: VecDeque<u16>,
-    active_containers: HashMap<u16, String>, // Map of port -> container_id
+    sizing: PoolSizing,
+    free_cvm_ports: Arc<Mutex<VecDeque<u16>>>,
+    active_containers: Arc<Mutex<HashMap<u16, String>>>, // Map of port -> container_id
+    /// When each currently-free port was pushed into `free_cvm_ports`, so the shrink half of
+    /// [`Self::sizing`]'s policy (see [`Self::shrink_idle_pool`]) knows which free containers
+    /// have sat idle past `idle_ttl`.
+    free_since: Arc<Mutex<HashMap<u16, std::time::Instant>>>,
+    /// Consecutive attestation failures observed per port, reset on a successful attest.
+    /// Consulted only by [`Self::reap_once`] to decide when a flaky container should be
+    /// evicted outright instead of retried again next sweep.
+    failure_counts: Arc<Mutex<HashMap<u16, u32>>>,
+    /// Which port a given `run_id` was assigned to, so operators can pull logs for a stuck run
+    /// (see [`Self::container_logs_for_run`]) without knowing its port.
+    active_runs: Arc<Mutex<HashMap<String, u16>>>,
+    /// Serializes [`Self::acquire`]'s check-and-grow decision so two concurrent callers can't
+    /// both observe the pool under `sizing.max_size` and each launch a new container, pushing
+    /// the pool past its configured cap.
+    growth_lock: Arc<Mutex<()>>,
+    /// The dedicated Docker network every pooled container is attached to (see
+    /// [`ManagedNetwork`]), isolating this `Manager`'s CVMs from any other's in the same daemon.
+    network: Arc<ManagedNetwork>,
+    wait_strategy: WaitStrategy,
+    startup_timeout: Duration,
+    container_resources: ContainerResources,
+    compose_service: Option<compose::Service>,
 }
 
 impl Manager {
+    /// Connects to Docker the way the `docker` CLI does for a TLS-secured daemon: when
+    /// `DOCKER_TLS_VERIFY` is set (and not `0`), connects to `DOCKER_HOST` over TLS with the
+    /// `key.pem`/`cert.pem`/`ca.pem` in `DOCKER_CERT_PATH`. Whenever `DOCKER_TLS_VERIFY` is
+    /// unset, it uses bollard's local socket/named pipe defaults instead. Needed to run
+    /// the CVM pool against a remote builder or a confidential-VM host with no local Docker
+    /// socket.
+    pub fn connect() -> Result<Docker> {
+        let tls_verify = std::env::var("DOCKER_TLS_VERIFY").is_ok_and(|v| v != "0" && !v.is_empty());
+
+        if !tls_verify {
+            return Docker::connect_with_socket_defaults()
+                .context("Failed to connect to Docker over the local socket");
+        }
+
+        let host = std::env::var("DOCKER_HOST")
+            .context("DOCKER_TLS_VERIFY is set but DOCKER_HOST is not")?;
+        let cert_path = std::env::var("DOCKER_CERT_PATH")
+            .context("DOCKER_TLS_VERIFY is set but DOCKER_CERT_PATH is not")?;
+        let cert_dir = std::path::Path::new(&cert_path);
+
+        Docker::connect_with_ssl(
+            &host,
+            &cert_dir.join("key.pem"),
+            &cert_dir.join("cert.pem"),
+            &cert_dir.join("ca.pem"),
+            120,
+            bollard::API_DEFAULT_VERSION,
+        )
+        .context("Failed to connect to Docker over TLS")
+    }
+
     pub async fn new(docker: Docker, pool_size: usize) -> Result<Self> {
-        let runner_image_name = "plgnai/nearai_cvm_runner".to_string(); // TDOO: fix to specific version.
+        Self::new_with_config(docker, pool_size, ManagerConfig::default()).await
+    }
+
+    /// Builds a pool from a named service in a `docker-compose.yaml`-style file (see
+    /// [`compose`]) instead of the hardcoded single-image setup -- the image, environment,
+    /// volumes, ports, and restart policy all come from `service_name`'s entry under
+    /// `services:` rather than from constants in this crate. Other [`ManagerConfig`] tunables
+    /// (wait strategy, startup timeout, resource limits, sizing) still apply on top, same as
+    /// with [`Self::new_with_config`].
+    pub async fn from_compose(
+        docker: Docker,
+        compose_path: &std::path::Path,
+        service_name: &str,
+        pool_size: usize,
+        config: ManagerConfig,
+    ) -> Result<Self> {
+        let compose = compose::DockerCompose::from_file(compose_path)?;
+        let mut service = compose.service(service_name)?.clone();
+        service.volumes = compose.resolve_volumes(&service);
+
+        Self::new_with_config(
+            docker,
+            pool_size,
+            ManagerConfig {
+                compose_service: Some(service),
+                ..config
+            },
+        )
+        .await
+    }
 
-        // Pull the image first
+    /// Same as [`Self::new`], but with an explicit [`ManagerConfig`] instead of the defaults
+    /// (attestation-based readiness, 60-second startup timeout, no resource limits).
+    pub async fn new_with_config(
+        docker: Docker,
+        pool_size: usize,
+        config: ManagerConfig,
+    ) -> Result<Self> {
+        let runner_image_name = config
+            .compose_service
+            .as_ref()
+            .map(|service| service.image.clone())
+            .unwrap_or_else(|| "plgnai/nearai_cvm_runner".to_string()); // TDOO: fix to specific version.
+
+        // Pull the image first. This can take minutes on a cold cache, so it must never be
+        // charged against a container's readiness budget -- only the post-start wait below is.
         tracing::info!("Pulling CVM runner image...");
         let image_name = runner_image_name.clone();
         let mut stream = docker.create_image(
@@ -67,25 +454,51 @@ impl Manager {
         }
         tracing::info!("CVM runner image pulled successfully");
 
-        let mut manager = Self {
+        // Give this Manager's pool its own Docker network, so it can't see (or collide with,
+        // at the daemon level) another Manager's containers in the same daemon.
+        let network = Arc::new(ManagedNetwork::create(docker.clone()).await?);
+
+        let sizing = config.sizing.unwrap_or_else(|| PoolSizing::fixed(pool_size));
+
+        let manager = Self {
             docker,
             runner_image_name,
-            free_cvm_ports: VecDeque::with_capacity(pool_size),
-            active_containers: HashMap::new(),
+            sizing,
+            free_cvm_ports: Arc::new(Mutex::new(VecDeque::with_capacity(pool_size))),
+            active_containers: Arc::new(Mutex::new(HashMap::new())),
+            free_since: Arc::new(Mutex::new(HashMap::new())),
+            failure_counts: Arc::new(Mutex::new(HashMap::new())),
+            active_runs: Arc::new(Mutex::new(HashMap::new())),
+            growth_lock: Arc::new(Mutex::new(())),
+            network,
+            wait_strategy: config.wait_strategy,
+            startup_timeout: config.startup_timeout,
+            container_resources: config.container_resources,
+            compose_service: config.compose_service,
         };
 
+        // Reconcile containers a previous, crashed instance of this Manager left running before
+        // starting a fresh pool on top of them -- otherwise every restart leaks one more
+        // container and host port.
+        manager.reconcile_orphans().await?;
+
         // Fill the CVM pool during initialization
         manager.fill_cvm_pool().await?;
 
+        // Keep the pool healthy for the life of the manager: periodically evict containers
+        // that fail attestation or have exited out from under us, and refill the pool.
+        manager.spawn_reaper();
+
         Ok(manager)
     }
 
-    pub async fn get_cvm(&mut self) -> Result<u16> {
+    pub async fn get_cvm(&self) -> Result<u16> {
         let mut port_to_return = None;
         let mut index_to_remove = None;
 
         // First, find a working CVM
-        for (i, &port) in self.free_cvm_ports.iter().enumerate() {
+        let free_ports: Vec<u16> = self.free_cvm_ports.lock().await.iter().copied().collect();
+        for (i, port) in free_ports.into_iter().enumerate() {
             let mut client = match cvm_client::CvmClient::new(
                 format!("https://localhost:{}", port).as_str(),
                 None,
@@ -104,17 +517,17 @@ impl Manager {
                     break;
                 }
                 Err(e) => {
-                    tracing::warn!("Failed to attest CVM on port {}: {}", port, e);
-
-                    // If attestation fails, the container might be in a bad state
-                    // Try to remove it from active_containers and clean it up later
-                    if let Some(container_id) = self.active_containers.get(&port) {
-                        tracing::warn!(
-                            "Container {} for port {} is in a bad state",
-                            container_id,
-                            port
-                        );
-                    }
+                    // If attestation fails, the container might be in a bad state. The
+                    // background reaper (see `reap_once`) is what actually evicts it; we just
+                    // skip over it here so a bad-state container isn't handed out. Pull its
+                    // recent logs into the warning so this isn't a dead end to debug.
+                    let logs = self.container_logs(port, ATTESTATION_FAILURE_LOG_TAIL).await;
+                    tracing::warn!(
+                        "Failed to attest CVM on port {}: {}; recent logs:\n{}",
+                        port,
+                        e,
+                        logs.unwrap_or_else(|e| format!("<failed to fetch logs: {}>", e))
+                    );
 
                     continue;
                 }
@@ -124,7 +537,8 @@ impl Manager {
         // If we found a working CVM, remove it from the pool and refill
         if let Some(port) = port_to_return {
             if let Some(index) = index_to_remove {
-                self.free_cvm_ports.remove(index);
+                self.free_cvm_ports.lock().await.remove(index);
+                self.free_since.lock().await.remove(&port);
                 // Note: We keep the container in active_containers so we can clean it up later
                 self.fill_cvm_pool().await?;
             }
@@ -134,15 +548,161 @@ impl Manager {
         Err(anyhow::anyhow!("No free CVM ports available"))
     }
 
-    async fn fill_cvm_pool(&mut self) -> Result<()> {
-        while self.free_cvm_ports.len() < self.free_cvm_ports.capacity() {
+    /// Pushes `port` into `free_cvm_ports` and stamps its idle-since time, so the shrink sweep
+    /// (see [`Self::shrink_idle_pool`]) can tell how long it's been sitting unused.
+    async fn mark_free(&self, port: u16) {
+        self.free_cvm_ports.lock().await.push_back(port);
+        self.free_since
+            .lock()
+            .await
+            .insert(port, std::time::Instant::now());
+    }
+
+    /// Returns a port [`Self::acquire`] handed out back to the free pool, making it eligible for
+    /// reuse and for the shrink sweep to reclaim once it's sat idle past `sizing.idle_ttl`.
+    /// An `acquire`d port is never implicitly returned to the free pool -- the caller must
+    /// `release` it once done.
+    pub async fn release(&self, port: u16) {
+        if !self.active_containers.lock().await.contains_key(&port) {
+            tracing::warn!(
+                "release() called for port {} which this Manager is not tracking",
+                port
+            );
+            return;
+        }
+
+        self.mark_free(port).await;
+    }
+
+    /// Like [`Self::get_cvm`], but grows the pool on demand -- up to `sizing.max_size` -- when
+    /// the free pool is empty, instead of only ever handing out what `fill_cvm_pool` pre-warmed
+    /// to `sizing.min_size`. Pair with [`Self::release`] once done with the returned port.
+    pub async fn acquire(&self) -> Result<u16> {
+        // Held for the whole check-and-grow decision (not just the reads), so two concurrent
+        // `acquire` calls can't both see the pool under `sizing.max_size` and each launch a
+        // container -- the second one re-checks after the first's growth has landed.
+        let _growth_guard = self.growth_lock.lock().await;
+        if self.free_cvm_ports.lock().await.is_empty() {
+            let total = self.active_containers.lock().await.len();
+            if total < self.sizing.max_size {
+                tracing::info!(
+                    "CVM pool empty with {}/{} capacity used; growing the pool",
+                    total,
+                    self.sizing.max_size
+                );
+                let port = self.add_cvm_to_pool().await?;
+                self.mark_free(port).await;
+            }
+        }
+        drop(_growth_guard);
+
+        self.get_cvm().await
+    }
+
+    /// Current free/in-use/total occupancy of the pool.
+    pub async fn pool_metrics(&self) -> PoolMetrics {
+        let free = self.free_cvm_ports.lock().await.len();
+        let total = self.active_containers.lock().await.len();
+        PoolMetrics {
+            free,
+            in_use: total.saturating_sub(free),
+            total,
+        }
+    }
+
+    /// Finds containers a previous instance of this `Manager` left running (tagged with
+    /// [`MANAGED_BY_LABEL_KEY`]=[`MANAGED_BY_LABEL_VALUE`] by `launch_container`), and either
+    /// re-adopts a still-attestable one into the pool or stops+removes it. Run once, at the
+    /// start of [`Self::new_with_config`], before the pool is filled -- a re-adopted container
+    /// counts toward `sizing.min_size` the same as one `fill_cvm_pool` would have created.
+    async fn reconcile_orphans(&self) -> Result<()> {
+        let filters = HashMap::from([(
+            "label".to_string(),
+            vec![format!("{}={}", MANAGED_BY_LABEL_KEY, MANAGED_BY_LABEL_VALUE)],
+        )]);
+
+        let orphans = self
+            .docker
+            .list_containers(Some(bollard::container::ListContainersOptions {
+                all: true,
+                filters,
+                ..Default::default()
+            }))
+            .await
+            .context("Failed to list containers while reconciling orphans")?;
+
+        for orphan in orphans {
+            let Some(container_id) = orphan.id else {
+                continue;
+            };
+
+            tracing::info!("Found orphaned CVM container {} from a prior run", container_id);
+
+            let container_info = match self.docker.inspect_container(&container_id, None).await {
+                Ok(info) => info,
+                Err(e) => {
+                    tracing::warn!("Failed to inspect orphan container {}: {}", container_id, e);
+                    continue;
+                }
+            };
+
+            let is_running = container_info
+                .state
+                .as_ref()
+                .and_then(|s| s.running)
+                .unwrap_or(false);
+
+            let host_port = is_running
+                .then(|| Self::host_port_of(&container_info))
+                .flatten();
+
+            let adopted = match host_port {
+                Some(port) => Self::attest_port(port).await,
+                None => false,
+            };
+
+            if adopted {
+                let port = host_port.expect("adopted implies a host port was found");
+                tracing::info!("Re-adopting orphan container {} on port {}", container_id, port);
+                self.active_containers
+                    .lock()
+                    .await
+                    .insert(port, container_id.clone());
+                self.mark_free(port).await;
+            } else {
+                tracing::warn!(
+                    "Orphan container {} is not attestable; stopping and removing it",
+                    container_id
+                );
+                self.stop_and_remove_container(&container_id).await;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Tops the free pool back up to `sizing.min_size` -- the floor half of the pool's elastic
+    /// sizing policy; growth past it only happens on demand, via [`Self::acquire`].
+    async fn fill_cvm_pool(&self) -> Result<()> {
+        while self.free_cvm_ports.lock().await.len() < self.sizing.min_size {
             let port = self.add_cvm_to_pool().await?;
-            self.free_cvm_ports.push_back(port);
+            self.mark_free(port).await;
         }
         Ok(())
     }
 
-    async fn add_cvm_to_pool(&mut self) -> Result<u16> {
+    async fn add_cvm_to_pool(&self) -> Result<u16> {
+        let container_config = match &self.compose_service {
+            Some(service) => service.to_container_config(&self.container_resources),
+            None => self.default_container_config(),
+        };
+
+        self.launch_container(container_config).await
+    }
+
+    /// Builds the hardcoded single-image, single-bind container config used when no
+    /// `docker-compose.yaml` service was supplied via [`ManagerConfig::compose_service`].
+    fn default_container_config(&self) -> Config<String> {
         // Set up port mapping for the API (port 443)
         // We'll expose port 443 of the container to a random port on the host
         let mut exposed_ports = HashMap::new();
@@ -160,19 +720,53 @@ impl Manager {
         );
 
         // Create host config with port bindings and volume mounts
+        let resources = &self.container_resources;
         let host_config = HostConfig {
             port_bindings: Some(port_bindings),
             binds: Some(vec!["/var/run/tappd.sock:/var/run/tappd.sock".to_string()]),
+            memory: resources.memory,
+            nano_cpus: resources.nano_cpus,
+            shm_size: resources.shm_size,
+            pids_limit: resources.pids_limit,
+            privileged: Some(resources.privileged),
+            cgroupns_mode: resources
+                .cgroupns_private
+                .then_some(bollard::models::HostConfigCgroupnsModeEnum::PRIVATE),
+            userns_mode: resources.userns_mode.clone(),
             ..Default::default()
         };
 
-        // Create container config
-        let container_config = Config {
+        Config {
             image: Some(self.runner_image_name.clone()),
             exposed_ports: Some(exposed_ports),
             host_config: Some(host_config),
             ..Default::default()
-        };
+        }
+    }
+
+    /// Creates and starts a container from `container_config`, waits for it to pass
+    /// `self.wait_strategy`, and returns the host port Docker assigned to its `443/tcp`
+    /// binding -- the CVM API port every service (hardcoded or compose-declared) exposes.
+    async fn launch_container(&self, mut container_config: Config<String>) -> Result<u16> {
+        // Tag every container we create with the managed-by label, so a restarted Manager can
+        // tell a leaked `cvm-*` container it owns apart from something else's container that
+        // merely happens to share the name prefix (see `reconcile_orphans`).
+        container_config
+            .labels
+            .get_or_insert_with(HashMap::new)
+            .insert(MANAGED_BY_LABEL_KEY.to_string(), MANAGED_BY_LABEL_VALUE.to_string());
+
+        // Attach the container to this pool's dedicated network instead of the daemon's
+        // default bridge, isolating it from any other Manager's containers.
+        match container_config.host_config.as_mut() {
+            Some(host_config) => host_config.network_mode = Some(self.network.name.clone()),
+            None => {
+                container_config.host_config = Some(HostConfig {
+                    network_mode: Some(self.network.name.clone()),
+                    ..Default::default()
+                })
+            }
+        }
 
         // Create container options
         let container_options = CreateContainerOptions {
@@ -187,6 +781,13 @@ impl Manager {
             .await
             .context("Failed to create CVM container")?;
 
+        // From here on, any `?` bails out before the container is registered in
+        // `active_containers` -- wrap it in a guard that stops+removes it on drop (including an
+        // early return via `?`, or a panic) so a failed inspect/readiness check can't leak it.
+        // Disarmed just before the happy-path return.
+        let guard =
+            CvmContainerGuard::new(self.docker.clone(), container.id.clone(), self.network.clone());
+
         self.docker
             .start_container::<String>(&container.id, None)
             .await
@@ -201,29 +802,235 @@ impl Manager {
             .await
             .context("Failed to inspect container")?;
 
-        // Extract the host port that was assigned to container port 443
-        if let Some(network_settings) = container_info.network_settings {
-            if let Some(ports) = network_settings.ports {
-                if let Some(bindings) = ports.get("443/tcp") {
-                    if let Some(bindings_vec) = bindings {
-                        if !bindings_vec.is_empty() {
-                            let binding = &bindings_vec[0];
-                            if let Some(host_port_str) = &binding.host_port {
-                                if let Ok(host_port) = host_port_str.parse::<u16>() {
-                                    // Store the port mapping and container ID
-                                    tracing::info!("CVM is accessible on host port {}", host_port);
-                                    self.active_containers
-                                        .insert(host_port, container.id.clone());
-                                    return Ok(host_port);
-                                }
-                            }
-                        }
-                    }
+        let host_port = Self::host_port_of(&container_info)
+            .ok_or_else(|| anyhow::anyhow!("Failed to get CVM port"))?;
+
+        tracing::info!("CVM is accessible on host port {}", host_port);
+        self.active_containers
+            .lock()
+            .await
+            .insert(host_port, container.id.clone());
+
+        // Only the readiness poll is bounded by startup_timeout; the image pull above already
+        // ran unconstrained.
+        if let Err(e) = self.wait_until_ready(&container.id, host_port).await {
+            // The guard will stop+remove the container; don't leave a dangling entry pointing
+            // at it in the meantime.
+            self.active_containers.lock().await.remove(&host_port);
+            return Err(e);
+        }
+
+        guard.disarm();
+        Ok(host_port)
+    }
+
+    /// Extracts the host port Docker assigned to a container's `443/tcp` binding, the CVM API
+    /// port every service (hardcoded or compose-declared) exposes.
+    fn host_port_of(info: &bollard::models::ContainerInspectResponse) -> Option<u16> {
+        info.network_settings
+            .as_ref()?
+            .ports
+            .as_ref()?
+            .get("443/tcp")?
+            .as_ref()?
+            .first()?
+            .host_port
+            .as_ref()?
+            .parse::<u16>()
+            .ok()
+    }
+
+    /// Extracts the container's IPv4 address on `self.network`, so callers can address a CVM
+    /// directly within the pool's dedicated network instead of only through its mapped port.
+    fn network_ip_of(&self, info: &bollard::models::ContainerInspectResponse) -> Option<String> {
+        info.network_settings
+            .as_ref()?
+            .networks
+            .as_ref()?
+            .get(&self.network.name)?
+            .ip_address
+            .clone()
+            .filter(|ip| !ip.is_empty())
+    }
+
+    /// Fetches the in-network IPv4 address of the container currently bound to `port`, alongside
+    /// which it's already reachable at `localhost:{port}` via its published port mapping.
+    pub async fn container_ip(&self, port: u16) -> Result<Option<String>> {
+        let container_id = self
+            .active_containers
+            .lock()
+            .await
+            .get(&port)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("No container tracked for port {}", port))?;
+
+        let container_info = self
+            .docker
+            .inspect_container(&container_id, None)
+            .await
+            .context("Failed to inspect container")?;
+
+        Ok(self.network_ip_of(&container_info))
+    }
+
+    /// Polls `self.wait_strategy` until it reports the container ready, bounded by the
+    /// strategy's own `timeout` when it carries one, or `self.startup_timeout` otherwise. Does
+    /// not include any part of the preceding image pull, which can legitimately take minutes
+    /// and would otherwise dominate the timeout budget.
+    async fn wait_until_ready(&self, container_id: &str, port: u16) -> Result<()> {
+        if matches!(self.wait_strategy, WaitStrategy::None) {
+            return Ok(());
+        }
+
+        let timeout = match &self.wait_strategy {
+            WaitStrategy::LogLine { timeout, .. } | WaitStrategy::TcpPort { timeout } => *timeout,
+            _ => self.startup_timeout,
+        };
+
+        let poll = async {
+            loop {
+                if self.probe_ready(container_id, port).await? {
+                    return Ok::<(), anyhow::Error>(());
+                }
+                tokio::time::sleep(READINESS_POLL_INTERVAL).await;
+            }
+        };
+
+        match tokio::time::timeout(timeout, poll).await {
+            Ok(result) => result,
+            Err(_) => Err(ReadinessError::StartupTimeout {
+                container_id: container_id.to_string(),
+                port,
+                strategy: self.wait_strategy.clone(),
+                timeout,
+            }
+            .into()),
+        }
+    }
+
+    /// A single readiness check, per `self.wait_strategy`. Returns `Ok(false)` (not an error)
+    /// for a check that simply hasn't succeeded yet, so the caller can keep polling.
+    async fn probe_ready(&self, container_id: &str, port: u16) -> Result<bool> {
+        match &self.wait_strategy {
+            WaitStrategy::None => Ok(true),
+            WaitStrategy::AttestationReady => {
+                let mut client = match cvm_client::CvmClient::new(
+                    format!("https://localhost:{}", port).as_str(),
+                    None,
+                ) {
+                    Ok(client) => client,
+                    Err(_) => return Ok(false),
+                };
+                Ok(client.attest().await.is_ok())
+            }
+            WaitStrategy::TcpPort { .. } => {
+                Ok(tokio::net::TcpStream::connect(("127.0.0.1", port))
+                    .await
+                    .is_ok())
+            }
+            WaitStrategy::LogLine { pattern, .. } => {
+                let logs = self.fetch_container_logs(container_id, "200").await?;
+                Ok(pattern.is_match(&logs))
+            }
+            WaitStrategy::HealthCheck { path } => {
+                let url = format!("https://localhost:{}{}", port, path);
+                let client = reqwest::Client::builder()
+                    .danger_accept_invalid_certs(true)
+                    .timeout(Duration::from_secs(5))
+                    .build()
+                    .context("Failed to build health-check HTTP client")?;
+                Ok(client
+                    .get(&url)
+                    .send()
+                    .await
+                    .map(|resp| resp.status().is_success())
+                    .unwrap_or(false))
+            }
+        }
+    }
+
+    /// Fetches the container's last `tail` lines of stdout/stderr, for [`WaitStrategy::LogLine`]
+    /// polling and for [`Self::container_logs`]'s debugging use.
+    async fn fetch_container_logs(&self, container_id: &str, tail: &str) -> Result<String> {
+        let mut stream = self.docker.logs(
+            container_id,
+            Some(LogsOptions::<String> {
+                stdout: true,
+                stderr: true,
+                tail: tail.to_string(),
+                ..Default::default()
+            }),
+        );
+
+        let mut logs = String::new();
+        while let Some(chunk) = stream.next().await {
+            match chunk {
+                Ok(output) => logs.push_str(&output.to_string()),
+                Err(e) => {
+                    tracing::warn!(
+                        "Failed to read logs for container {}: {}",
+                        container_id,
+                        e
+                    );
+                    break;
                 }
             }
         }
+        Ok(logs)
+    }
+
+    /// Fetches the last `tail` lines of stdout/stderr for the container currently bound to
+    /// `port`, so operators can see why a CVM won't attest without shelling into the host.
+    pub async fn container_logs(&self, port: u16, tail: &str) -> Result<String> {
+        let container_id = self
+            .active_containers
+            .lock()
+            .await
+            .get(&port)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("No container tracked for port {}", port))?;
+
+        self.fetch_container_logs(&container_id, tail).await
+    }
 
-        Err(anyhow::anyhow!("Failed to get CVM port"))
+    /// Same as [`Self::container_logs`], but looks the port up by the `run_id` an agent was
+    /// assigned under (see [`Self::assign_cvm`]), for debugging a stuck run.
+    pub async fn container_logs_for_run(&self, run_id: &str, tail: &str) -> Result<String> {
+        let port = self
+            .active_runs
+            .lock()
+            .await
+            .get(run_id)
+            .copied()
+            .ok_or_else(|| anyhow::anyhow!("No CVM is tracked for run_id {}", run_id))?;
+
+        self.container_logs(port, tail).await
+    }
+
+    /// Fetches a single resource-usage snapshot (CPU, memory, network, block I/O) for the
+    /// container currently bound to `port`.
+    pub async fn container_stats(&self, port: u16) -> Result<bollard::container::Stats> {
+        let container_id = self
+            .active_containers
+            .lock()
+            .await
+            .get(&port)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("No container tracked for port {}", port))?;
+
+        let mut stream = self.docker.stats(
+            &container_id,
+            Some(bollard::container::StatsOptions {
+                stream: false,
+                one_shot: true,
+            }),
+        );
+
+        stream
+            .next()
+            .await
+            .ok_or_else(|| anyhow::anyhow!("No stats returned for container {}", container_id))?
+            .context("Failed to fetch container stats")
     }
 
     pub async fn assign_cvm(
@@ -235,6 +1042,7 @@ impl Manager {
     ) -> Result<u16> {
         // Get a free CVM
         let port = self.get_cvm().await?;
+        self.active_runs.lock().await.insert(run_id.clone(), port);
 
         // Configure the CVM with the provided parameters
         self.configure_cvm(port, run_id, thread_id, agent_id, run_config)
@@ -256,8 +1064,15 @@ impl Manager {
             cvm_client::CvmClient::new(format!("https://localhost:{}", port).as_str(), None)
                 .context("Failed to create CVM client")?;
 
-        // Perform attestation to verify the CVM
-        client.attest().await.context("Failed to attest CVM")?;
+        // Perform attestation to verify the CVM. On failure, fold the container's recent logs
+        // into the error context instead of leaving the caller to go pull them separately.
+        if let Err(e) = client.attest().await {
+            let logs = self
+                .container_logs(port, ATTESTATION_FAILURE_LOG_TAIL)
+                .await
+                .unwrap_or_else(|e| format!("<failed to fetch logs: {}>", e));
+            return Err(e).with_context(|| format!("Failed to attest CVM; recent logs:\n{}", logs));
+        }
 
         // Create the assign request
         let assign_request = cvm_client::AssignRequest {
@@ -298,79 +1113,237 @@ impl Manager {
         Ok(())
     }
 
+    /// Stops (falling back to a kill) then force-removes a single container, tolerating and
+    /// just logging any failure -- shutdown and the reaper both need to reap best-effort rather
+    /// than bail out partway through a batch.
+    async fn stop_and_remove_container(&self, container_id: &str) {
+        let stop_options = StopContainerOptions { t: 10 };
+
+        tracing::info!("Stopping container: {}", container_id);
+        match self.docker.stop_container(container_id, Some(stop_options)).await {
+            Ok(_) => tracing::info!("Successfully stopped container: {}", container_id),
+            Err(e) => {
+                tracing::warn!("Failed to stop container {}: {}", container_id, e);
+                // Try to kill the container if stopping fails
+                match self.docker.kill_container::<String>(container_id, None).await {
+                    Ok(_) => tracing::info!("Successfully killed container: {}", container_id),
+                    Err(e) => tracing::warn!("Failed to kill container {}: {}", container_id, e),
+                }
+            }
+        }
+
+        let remove_options = RemoveContainerOptions {
+            force: true,
+            ..Default::default()
+        };
+
+        tracing::info!("Removing container: {}", container_id);
+        match self
+            .docker
+            .remove_container(container_id, Some(remove_options))
+            .await
+        {
+            Ok(_) => tracing::info!("Successfully removed container: {}", container_id),
+            Err(e) => tracing::warn!("Failed to remove container {}: {}", container_id, e),
+        }
+    }
+
     /// Shutdown the manager and clean up all containers
-    pub async fn shutdown(&mut self) -> Result<()> {
+    pub async fn shutdown(&self) -> Result<()> {
         tracing::info!("Shutting down Manager and cleaning up resources...");
 
-        // Collect all container IDs (both free and active)
+        // Collect all container IDs (both free and active) before draining either map --
+        // free-pool container IDs live in `active_containers` too, so draining it first would
+        // leave nothing for the free-port lookup below to find.
         let mut container_ids = Vec::new();
 
-        // Add container IDs from active_containers
-        for (port, container_id) in self.active_containers.drain() {
-            tracing::info!("Preparing to stop container for port {}", port);
-            container_ids.push(container_id);
-        }
+        let mut free_cvm_ports = self.free_cvm_ports.lock().await;
+        let mut active_containers = self.active_containers.lock().await;
 
         // Get container IDs for free ports
-        let free_ports: Vec<u16> = self.free_cvm_ports.drain(..).collect();
-        for port in free_ports {
-            if let Some(container_id) = self.active_containers.remove(&port) {
+        for port in free_cvm_ports.drain(..) {
+            if let Some(container_id) = active_containers.remove(&port) {
                 tracing::info!("Preparing to stop container for free port {}", port);
                 container_ids.push(container_id);
             }
         }
 
+        // Add container IDs from whatever's left in active_containers
+        for (port, container_id) in active_containers.drain() {
+            tracing::info!("Preparing to stop container for port {}", port);
+            container_ids.push(container_id);
+        }
+
+        // Clear the free CVM ports queue and active containers map
+        drop(free_cvm_ports);
+        drop(active_containers);
+        self.free_since.lock().await.clear();
+        self.failure_counts.lock().await.clear();
+
         // Stop and remove all containers
         for container_id in container_ids {
-            // Set a timeout for stopping containers (10 seconds)
-            let stop_options = bollard::container::StopContainerOptions { t: 10 };
+            self.stop_and_remove_container(&container_id).await;
+        }
 
-            tracing::info!("Stopping container: {}", container_id);
-            match self
-                .docker
-                .stop_container(&container_id, Some(stop_options))
-                .await
-            {
-                Ok(_) => tracing::info!("Successfully stopped container: {}", container_id),
+        tracing::info!("Manager shutdown complete");
+        Ok(())
+    }
+
+    /// Spawns the background task that keeps the pool healthy for the life of this `Manager`
+    /// (see [`Self::reap_once`]). The task holds a cloned handle into the same `Arc<Mutex<_>>`
+    /// pool state, so it runs independently of whoever is calling `get_cvm`/`assign_cvm`.
+    fn spawn_reaper(&self) {
+        let manager = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(REAP_INTERVAL);
+            loop {
+                interval.tick().await;
+                if let Err(e) = manager.reap_once().await {
+                    tracing::warn!("CVM pool reaper sweep failed: {}", e);
+                }
+            }
+        });
+    }
+
+    /// One sweep of the reaper: evicts any pooled container whose Docker state is
+    /// `exited`/`dead`, or whose attestation has now failed [`MAX_ATTESTATION_FAILURES`] times
+    /// in a row, then refills the pool back up to size.
+    async fn reap_once(&self) -> Result<()> {
+        let ports_and_containers: Vec<(u16, String)> = self
+            .active_containers
+            .lock()
+            .await
+            .iter()
+            .map(|(port, id)| (*port, id.clone()))
+            .collect();
+
+        for (port, container_id) in ports_and_containers {
+            let unhealthy = match self.docker.inspect_container(&container_id, None).await {
+                Ok(info) => {
+                    let exited = info
+                        .state
+                        .as_ref()
+                        .and_then(|s| s.status)
+                        .map(|status| {
+                            matches!(
+                                status,
+                                bollard::models::ContainerStateStatusEnum::EXITED
+                                    | bollard::models::ContainerStateStatusEnum::DEAD
+                            )
+                        })
+                        .unwrap_or(false);
+
+                    exited || self.attestation_failure_evicts(port).await
+                }
                 Err(e) => {
-                    tracing::warn!("Failed to stop container {}: {}", container_id, e);
-                    // Try to kill the container if stopping fails
-                    match self
-                        .docker
-                        .kill_container::<String>(&container_id, None)
-                        .await
-                    {
-                        Ok(_) => tracing::info!("Successfully killed container: {}", container_id),
-                        Err(e) => {
-                            tracing::warn!("Failed to kill container {}: {}", container_id, e)
-                        }
-                    }
+                    tracing::warn!(
+                        "Reaper failed to inspect container {} (port {}): {}",
+                        container_id,
+                        port,
+                        e
+                    );
+                    self.attestation_failure_evicts(port).await
                 }
+            };
+
+            if !unhealthy {
+                continue;
             }
 
-            // Set force removal option
-            let remove_options = bollard::container::RemoveContainerOptions {
-                force: true,
-                ..Default::default()
+            tracing::warn!(
+                "Reaping unhealthy CVM container {} on port {}",
+                container_id,
+                port
+            );
+            self.stop_and_remove_container(&container_id).await;
+            self.free_cvm_ports.lock().await.retain(|p| *p != port);
+            self.free_since.lock().await.remove(&port);
+            self.active_containers.lock().await.remove(&port);
+            self.failure_counts.lock().await.remove(&port);
+        }
+
+        // Reclaim idle containers past their TTL before topping back up to `min_size`, so a
+        // pool that grew via `acquire` actually shrinks again instead of fighting the refill.
+        self.shrink_idle_pool().await;
+
+        self.fill_cvm_pool().await
+    }
+
+    /// Stops and removes free containers that have sat idle past `sizing.idle_ttl`, once the
+    /// free count exceeds `sizing.min_size` -- the shrink half of the elastic sizing policy
+    /// [`Self::acquire`] grows. Run once per reaper sweep, alongside the unhealthy-container
+    /// eviction above.
+    async fn shrink_idle_pool(&self) {
+        loop {
+            let now = std::time::Instant::now();
+
+            let candidate = {
+                let free_cvm_ports = self.free_cvm_ports.lock().await;
+                if free_cvm_ports.len() <= self.sizing.min_size {
+                    break;
+                }
+
+                let free_since = self.free_since.lock().await;
+                free_cvm_ports
+                    .iter()
+                    .copied()
+                    .filter(|port| {
+                        free_since
+                            .get(port)
+                            .is_some_and(|since| now.duration_since(*since) >= self.sizing.idle_ttl)
+                    })
+                    .min_by_key(|port| free_since.get(port).copied())
             };
 
-            tracing::info!("Removing container: {}", container_id);
-            match self
-                .docker
-                .remove_container(&container_id, Some(remove_options))
-                .await
-            {
-                Ok(_) => tracing::info!("Successfully removed container: {}", container_id),
-                Err(e) => tracing::warn!("Failed to remove container {}: {}", container_id, e),
+            let Some(port) = candidate else {
+                break;
+            };
+
+            tracing::info!(
+                "Shrinking CVM pool: reclaiming port {} idle past {:?}",
+                port,
+                self.sizing.idle_ttl
+            );
+            self.free_cvm_ports.lock().await.retain(|p| *p != port);
+            self.free_since.lock().await.remove(&port);
+            if let Some(container_id) = self.active_containers.lock().await.remove(&port) {
+                self.stop_and_remove_container(&container_id).await;
             }
+            self.failure_counts.lock().await.remove(&port);
         }
+    }
 
-        // Clear the free CVM ports queue and active containers map
-        self.free_cvm_ports.clear();
-        self.active_containers.clear();
+    /// Attests the CVM on `port` once, with no side effects on `failure_counts`. Used both by
+    /// [`Self::attestation_failure_evicts`] and by [`Self::reconcile_orphans`], which wants a
+    /// single-shot check rather than the reaper's failures-in-a-row tolerance.
+    async fn attest_port(port: u16) -> bool {
+        match cvm_client::CvmClient::new(format!("https://localhost:{}", port).as_str(), None) {
+            Ok(mut client) => client.attest().await.is_ok(),
+            Err(_) => false,
+        }
+    }
 
-        tracing::info!("Manager shutdown complete");
-        Ok(())
+    /// Attests `port`'s CVM once, bumping (or resetting) its entry in `failure_counts`.
+    /// Returns whether the port has now failed attestation `MAX_ATTESTATION_FAILURES` times in
+    /// a row and should be evicted instead of given another sweep to recover.
+    async fn attestation_failure_evicts(&self, port: u16) -> bool {
+        let attested = Self::attest_port(port).await;
+
+        let mut failure_counts = self.failure_counts.lock().await;
+        if attested {
+            failure_counts.remove(&port);
+            false
+        } else {
+            let count = failure_counts.entry(port).or_insert(0);
+            *count += 1;
+            tracing::warn!(
+                "CVM on port {} failed attestation ({}/{} consecutive failures)",
+                port,
+                count,
+                MAX_ATTESTATION_FAILURES
+            );
+            *count >= MAX_ATTESTATION_FAILURES
+        }
     }
 }
 
@@ -657,16 +1630,15 @@ mod tests {
         // Connect to Docker
         let docker = Docker::connect_with_socket_defaults().unwrap();
 
-        // Create a Manager with a small pool size
+        // Build a Manager with a small fixed pool size via the public constructor, rather than a
+        // Manager { .. } struct literal -- the pool fields are internal and change shape as the
+        // pool-sizing/reaper machinery grows (see `Manager`'s doc comment).
         let pool_size = 2;
-        let mut manager = Manager {
-            docker: docker.clone(),
-            runner_image_name: "plgnai/nearai_cvm_runner".to_string(),
-            free_cvm_ports: VecDeque::with_capacity(pool_size),
-            active_containers: HashMap::new(),
-        };
+        let manager = Manager::new_with_config(docker.clone(), pool_size, ManagerConfig::default())
+            .await
+            .unwrap();
 
-        // Fill the pool
+        // Fill the pool again on top of what `new_with_config` already filled.
         let result = manager.fill_cvm_pool().await;
 
         // Verify the result is Ok
@@ -677,16 +1649,18 @@ mod tests {
         );
 
         // Verify the pool is filled with the expected number of ports
+        let free_ports = manager.free_cvm_ports.lock().await;
         assert_eq!(
-            manager.free_cvm_ports.len(),
+            free_ports.len(),
             pool_size,
             "Pool size doesn't match expected size"
         );
 
         // Verify each port is valid (non-zero)
-        for port in &manager.free_cvm_ports {
+        for port in free_ports.iter() {
             assert!(*port > 0, "Invalid port number: {}", port);
         }
+        drop(free_ports);
 
         // Clean up the containers after test
         // We need to get the container IDs from the Docker API
@@ -734,16 +1708,18 @@ mod tests {
         let manager = manager_result.unwrap();
 
         // Verify the pool is filled with the expected number of ports
+        let free_ports = manager.free_cvm_ports.lock().await;
         assert_eq!(
-            manager.free_cvm_ports.len(),
+            free_ports.len(),
             pool_size,
             "Pool size doesn't match expected size"
         );
 
         // Verify each port is valid (non-zero)
-        for port in &manager.free_cvm_ports {
+        for port in free_ports.iter() {
             assert!(*port > 0, "Invalid port number: {}", port);
         }
+        drop(free_ports);
 
         // Clean up the containers after test
         // We need to get the container IDs from the Docker API
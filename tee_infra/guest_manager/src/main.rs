@@ -1,10 +1,9 @@
 use axum::{
     Json, Router,
-    extract::State,
+    extract::{Path, State},
     http::StatusCode,
     routing::{get, post},
 };
-use bollard::Docker;
 use guest_manager::{Manager, RunConfig};
 use near_auth::{AuthData, verify_signed_message};
 use serde::{Deserialize, Serialize};
@@ -40,6 +39,14 @@ struct AssignCvmRequest {
 #[derive(Serialize)]
 struct AssignCvmResponse {
     port: u16,
+    /// The CVM's in-network IPv4 address, if the pool's network returned one.
+    ip: Option<String>,
+}
+
+// Response for the run logs endpoint
+#[derive(Serialize)]
+struct RunLogsResponse {
+    logs: String,
 }
 
 #[tokio::main]
@@ -52,8 +59,9 @@ async fn main() -> anyhow::Result<()> {
         .with(tracing_subscriber::fmt::layer())
         .init();
 
-    // Connect to Docker
-    let docker = Docker::connect_with_socket_defaults()?;
+    // Connect to Docker, honoring DOCKER_HOST/DOCKER_TLS_VERIFY/DOCKER_CERT_PATH so this can
+    // run against a remote or TLS-secured daemon instead of only a local socket.
+    let docker = Manager::connect()?;
 
     // Create the Manager with a pool size of 5
     let manager = Manager::new(docker, 5).await?;
@@ -74,6 +82,7 @@ async fn main() -> anyhow::Result<()> {
     let app = Router::new()
         .route("/health", get(health_check))
         .route("/assign_cvm", post(assign_cvm))
+        .route("/runs/{run_id}/logs", get(run_logs))
         .with_state(state)
         .layer(TraceLayer::new_for_http());
 
@@ -136,6 +145,22 @@ async fn health_check() -> StatusCode {
     StatusCode::OK
 }
 
+// Fetch recent container logs for a run, so operators can debug a stuck run without shelling
+// into the host.
+async fn run_logs(
+    State(state): State<Arc<AppState>>,
+    Path(run_id): Path<String>,
+) -> Result<Json<RunLogsResponse>, (StatusCode, String)> {
+    let manager = state.manager.lock().await;
+    match manager.container_logs_for_run(&run_id, "200").await {
+        Ok(logs) => Ok(Json(RunLogsResponse { logs })),
+        Err(e) => {
+            tracing::error!("Failed to fetch logs for run {}: {}", run_id, e);
+            Err((StatusCode::NOT_FOUND, e.to_string()))
+        }
+    }
+}
+
 // Assign CVM endpoint
 #[axum::debug_handler]
 async fn assign_cvm(
@@ -171,7 +196,10 @@ async fn assign_cvm(
         )
         .await
     {
-        Ok(port) => Ok(Json(AssignCvmResponse { port })),
+        Ok(port) => {
+            let ip = manager.container_ip(port).await.ok().flatten();
+            Ok(Json(AssignCvmResponse { port, ip }))
+        }
         Err(e) => {
             tracing::error!("Failed to assign CVM: {}", e);
             Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
@@ -0,0 +1,239 @@
+//! Layered `ServerConfig` loader: reads `nearai-host.toml`, overlays `NEARAI_*` environment
+//! variables, validates the result, and hands back a ready-to-use `Arc<ServerConfig>`.
+
+use crate::ServerConfig;
+use anyhow::{anyhow, bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::net::ToSocketAddrs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// On-disk representation of `nearai-host.toml`. Every field is optional so a partial (or
+/// entirely absent) file can be overlaid with environment variables and defaults.
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct ServerConfigFile {
+    kp_address: Option<String>,
+    kp_port: Option<u16>,
+    vm_dir: Option<PathBuf>,
+    #[serde(default)]
+    logging: LoggingConfigFile,
+    #[serde(default)]
+    diagnostics: DiagnosticsConfigFile,
+    #[serde(default)]
+    shutdown: ShutdownConfigFile,
+}
+
+/// `[shutdown]` table in `nearai-host.toml`, controlling how long a graceful shutdown waits
+/// for in-flight requests to drain before the process exits anyway.
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+pub struct ShutdownConfigFile {
+    /// How long to wait for in-flight requests to finish after a shutdown signal before
+    /// exiting regardless. Defaults to 30 seconds.
+    pub drain_timeout_secs: Option<u64>,
+}
+
+impl ShutdownConfigFile {
+    /// The configured drain timeout, or the 30-second default if unset.
+    pub fn drain_timeout(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.drain_timeout_secs.unwrap_or(30))
+    }
+}
+
+/// Reads just the `[shutdown]` table from `path`. Returns the default (30-second timeout) table
+/// if the file is missing or malformed.
+pub fn peek_shutdown_config(path: &Path) -> ShutdownConfigFile {
+    #[derive(Deserialize, Default)]
+    struct Peek {
+        #[serde(default)]
+        shutdown: ShutdownConfigFile,
+    }
+
+    std::fs::read_to_string(path)
+        .ok()
+        .and_then(|contents| toml::from_str::<Peek>(&contents).ok())
+        .map(|p| p.shutdown)
+        .unwrap_or_default()
+}
+
+/// `[diagnostics]` table in `nearai-host.toml`, for opt-in runtime introspection features that
+/// are otherwise off by default.
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+pub struct DiagnosticsConfigFile {
+    /// Enables the tokio-console gRPC diagnostics endpoint (also requires the crate to be
+    /// built with the `tokio-console` feature). Defaults to `false`.
+    pub tokio_console: Option<bool>,
+}
+
+/// Reads just the `[diagnostics]` table from `path`. Returns the default (all-disabled) table
+/// if the file is missing or malformed.
+pub fn peek_diagnostics_config(path: &Path) -> DiagnosticsConfigFile {
+    #[derive(Deserialize, Default)]
+    struct Peek {
+        #[serde(default)]
+        diagnostics: DiagnosticsConfigFile,
+    }
+
+    std::fs::read_to_string(path)
+        .ok()
+        .and_then(|contents| toml::from_str::<Peek>(&contents).ok())
+        .map(|p| p.diagnostics)
+        .unwrap_or_default()
+}
+
+/// `[logging]` table in `nearai-host.toml`. Read separately from the rest of the config (see
+/// [`peek_logging_config`]) since the log format needs to be chosen before the rest of the
+/// config can be loaded and validated through a subscriber.
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+pub struct LoggingConfigFile {
+    /// `"json"` or `"text"`; defaults to `"text"` if unset.
+    pub format: Option<String>,
+    /// Directory to write rolling log files to, in addition to stdout. Unset disables file
+    /// logging entirely.
+    pub directory: Option<PathBuf>,
+    /// Prefix for rolling log file names; defaults to `"nearai-host"`.
+    pub filename_prefix: Option<String>,
+    /// Rotation policy for the log file: `"hourly"`, `"daily"`, or `"never"`. Defaults to
+    /// `"daily"`.
+    pub rotation: Option<String>,
+}
+
+/// Reads just the `[logging]` table from `path`, without requiring the rest of the config to
+/// parse or validate successfully. Returns the default (empty) table if the file is missing
+/// or malformed.
+pub fn peek_logging_config(path: &Path) -> LoggingConfigFile {
+    #[derive(Deserialize, Default)]
+    struct Peek {
+        #[serde(default)]
+        logging: LoggingConfigFile,
+    }
+
+    std::fs::read_to_string(path)
+        .ok()
+        .and_then(|contents| toml::from_str::<Peek>(&contents).ok())
+        .map(|p| p.logging)
+        .unwrap_or_default()
+}
+
+const DEFAULT_CONFIG_TEMPLATE: &str = r#"# nearai-host.toml
+#
+# Configuration for the nearai host API server. Commented-out fields fall back to their
+# built-in default, then to the matching NEARAI_* environment variable if that's set instead.
+
+# Hostname or IP of the key provider to forward GetSealingKey requests to.
+# kp_address = "localhost"
+
+# Port the key provider listens on.
+# kp_port = 3443
+
+# Directory containing the instance's shared/ folder, where instance.info is written.
+# vm_dir = "/tmp"
+
+[logging]
+# Log output format: "text" for human-readable, "json" for a bunyan-style structured line
+# per event. Overridden by the NEARAI_LOG_FORMAT environment variable if that's set.
+# format = "text"
+
+# Directory to additionally write rolling log files to, alongside stdout. Leave unset to
+# disable file logging.
+# directory = "/var/log/nearai-host"
+
+# Prefix for rolling log file names.
+# filename_prefix = "nearai-host"
+
+# Rotation policy for the log file: "hourly", "daily", or "never".
+# rotation = "daily"
+
+[diagnostics]
+# Enables the tokio-console gRPC diagnostics endpoint. Requires the binary to be built with
+# the `tokio-console` feature. Overridden by NEARAI_TOKIO_CONSOLE if that's set.
+# tokio_console = false
+
+[shutdown]
+# How long, in seconds, to wait for in-flight requests to drain after a Ctrl-C/SIGTERM before
+# exiting anyway.
+# drain_timeout_secs = 30
+"#;
+
+/// Writes a commented default config file to `path`. Refuses to overwrite an existing file so
+/// operators don't accidentally clobber a tuned config.
+pub fn init_config_file(path: &Path) -> Result<()> {
+    if path.exists() {
+        bail!(
+            "Config file already exists at {}; refusing to overwrite",
+            path.display()
+        );
+    }
+    std::fs::write(path, DEFAULT_CONFIG_TEMPLATE)
+        .with_context(|| format!("Failed to write default config to {}", path.display()))
+}
+
+/// Loads a [`ServerConfig`] by reading `path` (if present), overlaying `NEARAI_KP_ADDRESS`,
+/// `NEARAI_KP_PORT`, and `NEARAI_VM_DIR` environment variables over whatever it finds there,
+/// then validating the result. Environment variables take priority over the file so operators
+/// can override a shared config without editing it.
+pub fn load_config(path: &Path) -> Result<Arc<ServerConfig>> {
+    let file: ServerConfigFile = if path.exists() {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config file {}", path.display()))?;
+        toml::from_str(&contents)
+            .with_context(|| format!("Failed to parse config file {}", path.display()))?
+    } else {
+        tracing::warn!(
+            "No config file at {}; using defaults and environment overrides",
+            path.display()
+        );
+        ServerConfigFile::default()
+    };
+
+    let kp_address = std::env::var("NEARAI_KP_ADDRESS")
+        .ok()
+        .or(file.kp_address)
+        .unwrap_or_else(|| "localhost".to_string());
+
+    let kp_port = std::env::var("NEARAI_KP_PORT")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .or(file.kp_port)
+        .unwrap_or(3443);
+
+    let vm_dir = std::env::var("NEARAI_VM_DIR")
+        .ok()
+        .map(PathBuf::from)
+        .or(file.vm_dir)
+        .unwrap_or_else(|| PathBuf::from("/tmp"));
+
+    validate(&kp_address, kp_port, &vm_dir)?;
+
+    Ok(Arc::new(ServerConfig {
+        kp_address,
+        kp_port,
+        vm_dir,
+    }))
+}
+
+/// Validates that `kp_port` is a usable port, `vm_dir` exists and is writable, and
+/// `kp_address` resolves to at least one socket address.
+fn validate(kp_address: &str, kp_port: u16, vm_dir: &Path) -> Result<()> {
+    if kp_port == 0 {
+        bail!("kp_port must be a non-zero port number");
+    }
+
+    if !vm_dir.exists() {
+        bail!("vm_dir '{}' does not exist", vm_dir.display());
+    }
+    if !vm_dir.is_dir() {
+        bail!("vm_dir '{}' is not a directory", vm_dir.display());
+    }
+    let probe = vm_dir.join(".nearai-host-write-check");
+    std::fs::write(&probe, b"")
+        .with_context(|| format!("vm_dir '{}' is not writable", vm_dir.display()))?;
+    let _ = std::fs::remove_file(&probe);
+
+    (kp_address, kp_port)
+        .to_socket_addrs()
+        .with_context(|| format!("kp_address '{}' is not resolvable", kp_address))?
+        .next()
+        .ok_or_else(|| anyhow!("kp_address '{}' resolved to no addresses", kp_address))?;
+
+    Ok(())
+}
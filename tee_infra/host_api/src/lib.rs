@@ -4,6 +4,7 @@ use std::net::{SocketAddr, TcpStream};
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::thread;
+use std::time::{Duration, Instant};
 
 use anyhow;
 use axum::{
@@ -17,7 +18,11 @@ use axum::{
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 use tokio::net::TcpListener;
+use tokio::sync::watch;
 use tower_http::trace::TraceLayer;
+use uuid::Uuid;
+
+pub mod config;
 
 #[derive(Clone, Debug)]
 pub struct ServerConfig {
@@ -38,6 +43,77 @@ impl ServerConfig {
     }
 }
 
+/// A cooperative trigger for a graceful server shutdown.
+///
+/// A handle is created by the caller and passed into [`start_server`], which both listens for
+/// it being triggered externally (e.g. by a test, or another subsystem that needs to coordinate
+/// teardown) and installs its own Ctrl-C/SIGTERM listener that triggers it internally. Cloning
+/// and calling [`ShutdownHandle::trigger`] from anywhere is safe, including after the server has
+/// already shut down.
+#[derive(Clone)]
+pub struct ShutdownHandle {
+    tx: watch::Sender<bool>,
+}
+
+impl ShutdownHandle {
+    /// Creates a new, untriggered handle.
+    pub fn new() -> Self {
+        let (tx, _rx) = watch::channel(false);
+        Self { tx }
+    }
+
+    /// Signals that the server should stop accepting new connections and begin draining
+    /// in-flight requests.
+    pub fn trigger(&self) {
+        let _ = self.tx.send(true);
+    }
+
+    /// Resolves once [`ShutdownHandle::trigger`] has been called, or immediately if it already
+    /// has been.
+    async fn triggered(&self) {
+        let mut rx = self.tx.subscribe();
+        if *rx.borrow() {
+            return;
+        }
+        let _ = rx.changed().await;
+    }
+}
+
+impl Default for ShutdownHandle {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Waits for either a Ctrl-C (SIGINT) or, on Unix, a SIGTERM, then triggers `shutdown`.
+async fn listen_for_shutdown_signals(shutdown: ShutdownHandle) {
+    let ctrl_c = async {
+        let _ = tokio::signal::ctrl_c().await;
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut sigterm) => {
+                sigterm.recv().await;
+            }
+            Err(err) => {
+                tracing::error!("Failed to install SIGTERM handler: {}", err);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => tracing::info!("Received Ctrl-C, starting graceful shutdown"),
+        _ = terminate => tracing::info!("Received SIGTERM, starting graceful shutdown"),
+    }
+
+    shutdown.trigger();
+}
+
 #[derive(Error, Debug)]
 pub enum QuoteError {
     #[error("IO error: {0}")]
@@ -327,10 +403,72 @@ async fn notify(
     Ok(Json(serde_json::json!(null)))
 }
 
-/// Starts the host API server with the given configuration
+/// Header a caller can set to propagate its own correlation id into the request span;
+/// generated with [`Uuid::new_v4`] when absent.
+const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Builds the per-request tracing span: method, path, the VM this server instance is fronting,
+/// and a correlation id (honored from [`REQUEST_ID_HEADER`] if the caller supplied one,
+/// generated otherwise). Every log line emitted while handling the request, including the
+/// `get_key` call out to the key provider, is emitted inside this span and so inherits these
+/// fields.
+fn request_span(
+    config: Arc<ServerConfig>,
+) -> impl Fn(&axum::http::Request<axum::body::Body>) -> tracing::Span + Clone {
+    move |request| {
+        let request_id = request
+            .headers()
+            .get(REQUEST_ID_HEADER)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string)
+            .unwrap_or_else(|| Uuid::new_v4().to_string());
+
+        let vm_id = config
+            .vm_dir
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| config.vm_dir.display().to_string());
+
+        tracing::info_span!(
+            "http_request",
+            method = %request.method(),
+            path = %request.uri().path(),
+            vm_id = %vm_id,
+            request_id = %request_id,
+        )
+    }
+}
+
+/// Logs the outcome of a request right before its span closes, so latency and status land
+/// inside the same span as every other log line for the request.
+fn on_request_response(
+    response: &axum::http::Response<axum::body::Body>,
+    latency: Duration,
+    _span: &tracing::Span,
+) {
+    tracing::info!(
+        status = response.status().as_u16(),
+        latency_ms = latency.as_millis() as u64,
+        "request completed"
+    );
+}
+
+/// Starts the host API server with the given configuration, serving until a graceful shutdown
+/// completes.
 ///
-/// This function can be called from another binary to start the server in a thread.
-pub async fn start_server(config: Arc<ServerConfig>) -> anyhow::Result<()> {
+/// `shutdown` is both an input and an output: the caller can trigger it directly (e.g. to
+/// coordinate teardown with another subsystem), and `start_server` also triggers it itself when
+/// it receives Ctrl-C or SIGTERM. Either way, the server stops accepting new connections and
+/// drains in-flight requests for up to `shutdown_timeout` before this function returns; requests
+/// still running past that deadline are abandoned so the process can exit. Each request opens
+/// its own short-lived connection to the key provider (see [`get_key`]), so there's no shared
+/// channel to close separately: it closes on its own once the draining request finishes or is
+/// abandoned.
+pub async fn start_server(
+    config: Arc<ServerConfig>,
+    shutdown: ShutdownHandle,
+    shutdown_timeout: Duration,
+) -> anyhow::Result<()> {
     tracing::info!("Starting host API server with config: {:?}", config);
 
     // Build our application with routes
@@ -338,7 +476,11 @@ pub async fn start_server(config: Arc<ServerConfig>) -> anyhow::Result<()> {
         .route("/api/GetSealingKey", post(get_sealing_key))
         .route("/api/Notify", post(notify))
         .with_state(config.clone())
-        .layer(TraceLayer::new_for_http());
+        .layer(
+            TraceLayer::new_for_http()
+                .make_span_with(request_span(config.clone()))
+                .on_response(on_request_response),
+        );
     tracing::debug!("Router configured with endpoints: /api/GetSealingKey, /api/Notify");
 
     // Run the server
@@ -356,14 +498,44 @@ pub async fn start_server(config: Arc<ServerConfig>) -> anyhow::Result<()> {
     let server_addr = listener.local_addr()?;
     tracing::info!("Server listening on http://{}", server_addr);
 
+    tokio::spawn(listen_for_shutdown_signals(shutdown.clone()));
+
+    let shutdown_for_wait = shutdown.clone();
+    let serve_task = tokio::spawn(async move {
+        axum::serve(listener, app)
+            .with_graceful_shutdown(async move { shutdown_for_wait.triggered().await })
+            .await
+    });
+
     tracing::info!("Starting to serve requests");
-    if let Err(err) = axum::serve(listener, app).await {
-        tracing::error!("Server error: {}", err);
-        return Err(err.into());
+    shutdown.triggered().await;
+    tracing::info!("Draining in-flight requests before exiting");
+    let drain_started = Instant::now();
+
+    match tokio::time::timeout(shutdown_timeout, serve_task).await {
+        Ok(Ok(Ok(()))) => {
+            tracing::info!(
+                drain_ms = drain_started.elapsed().as_millis() as u64,
+                "Server shutdown complete"
+            );
+            Ok(())
+        }
+        Ok(Ok(Err(err))) => {
+            tracing::error!("Server error: {}", err);
+            Err(err.into())
+        }
+        Ok(Err(join_err)) => {
+            tracing::error!("Server task panicked: {}", join_err);
+            Err(join_err.into())
+        }
+        Err(_) => {
+            tracing::warn!(
+                timeout_ms = shutdown_timeout.as_millis() as u64,
+                "Graceful shutdown timed out while draining in-flight requests; exiting anyway"
+            );
+            Ok(())
+        }
     }
-
-    tracing::info!("Server shutdown complete");
-    Ok(())
 }
 
 /// Starts the host API server in a new thread
@@ -414,7 +586,11 @@ pub fn start_server_in_thread(
                 .route("/api/GetSealingKey", post(get_sealing_key))
                 .route("/api/Notify", post(notify))
                 .with_state(config.clone())
-                .layer(TraceLayer::new_for_http());
+                .layer(
+                    TraceLayer::new_for_http()
+                        .make_span_with(request_span(config.clone()))
+                        .on_response(on_request_response),
+                );
             tracing::debug!("Router configured with endpoints: /api/GetSealingKey, /api/Notify");
 
             tracing::info!("Server listening on http://{}", server_addr);
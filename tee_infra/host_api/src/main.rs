@@ -1,28 +1,224 @@
-use host_api::{start_server, ServerConfig};
-use std::path::PathBuf;
-use std::sync::Arc;
-use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+use clap::{Parser, Subcommand};
+use host_api::{config, start_server, ShutdownHandle};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::Rotation;
+use tracing_bunyan_formatter::{BunyanFormattingLayer, JsonStorageLayer};
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, Layer};
+
+/// CLI for the nearai host API server
+#[derive(Parser)]
+#[command(author, version, about, long_about = None)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Commands>,
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    /// Start the server (the default if no subcommand is given)
+    Serve {
+        /// Path to the config file
+        #[arg(short, long, default_value = "nearai-host.toml")]
+        config: PathBuf,
+    },
+    /// Write a commented default config file, without overwriting an existing one
+    Init {
+        /// Path to write the config file to
+        #[arg(short, long, default_value = "nearai-host.toml")]
+        config: PathBuf,
+    },
+}
+
+/// Log output format, selected via `[logging] format` in the config file or the
+/// `NEARAI_LOG_FORMAT` environment variable (which takes priority).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LogFormat {
+    Text,
+    Json,
+}
+
+impl LogFormat {
+    fn resolve(config_path: &Path) -> Self {
+        let from_env = std::env::var("NEARAI_LOG_FORMAT").ok();
+        let from_file = config::peek_logging_config(config_path).format;
+
+        match from_env.or(from_file).as_deref() {
+            Some("json") => LogFormat::Json,
+            Some("text") | None => LogFormat::Text,
+            Some(other) => {
+                eprintln!("Unknown log format '{}', falling back to text", other);
+                LogFormat::Text
+            }
+        }
+    }
+}
+
+/// Whether the tokio-console diagnostics endpoint is enabled, via `[diagnostics] tokio_console`
+/// in the config file or the `NEARAI_TOKIO_CONSOLE` environment variable (which takes
+/// priority). Has no effect unless the binary is also built with the `tokio-console` feature.
+fn tokio_console_enabled(config_path: &Path) -> bool {
+    std::env::var("NEARAI_TOKIO_CONSOLE")
+        .ok()
+        .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+        .or(config::peek_diagnostics_config(config_path).tokio_console)
+        .unwrap_or(false)
+}
+
+/// Builds the tokio-console layer when the `tokio-console` feature is compiled in and
+/// `enabled` is true. It carries its own target-based filtering internally, so it's added to
+/// the registry independently of the `EnvFilter` driving the other layers.
+#[cfg(feature = "tokio-console")]
+fn console_layer(enabled: bool) -> Option<console_subscriber::ConsoleLayer> {
+    enabled.then(|| console_subscriber::ConsoleLayer::builder().spawn())
+}
+
+#[cfg(not(feature = "tokio-console"))]
+fn console_layer(enabled: bool) -> Option<tracing_subscriber::layer::Identity> {
+    if enabled {
+        eprintln!("NEARAI_TOKIO_CONSOLE/diagnostics.tokio_console is set, but this binary was not built with the `tokio-console` feature; ignoring");
+    }
+    None
+}
+
+/// Parses the `[logging] rotation` config value into a `tracing_appender` rotation policy.
+/// `tracing-appender` has no native size-based rotation, so `"size"` falls back to daily with a
+/// warning; unknown values do the same.
+fn parse_rotation(rotation: Option<&str>) -> Rotation {
+    match rotation {
+        Some("hourly") => Rotation::HOURLY,
+        Some("daily") | None => Rotation::DAILY,
+        Some("never") => Rotation::NEVER,
+        Some(other) => {
+            eprintln!(
+                "Unsupported log rotation '{}' (tracing-appender has no size-based rotation), falling back to daily",
+                other
+            );
+            Rotation::DAILY
+        }
+    }
+}
+
+/// Builds the rolling file log layer when `[logging] directory` is set. Returns the layer
+/// together with the `WorkerGuard` for its non-blocking writer; the guard must be held for the
+/// lifetime of the process, or buffered lines are dropped instead of flushed on shutdown.
+fn file_log_layer(
+    logging: &config::LoggingConfigFile,
+) -> Option<(
+    Box<dyn Layer<tracing_subscriber::Registry> + Send + Sync>,
+    WorkerGuard,
+)> {
+    let directory = logging.directory.as_ref()?;
+    let filename_prefix = logging
+        .filename_prefix
+        .as_deref()
+        .unwrap_or("nearai-host");
+
+    let appender = tracing_appender::rolling::RollingFileAppender::new(
+        parse_rotation(logging.rotation.as_deref()),
+        directory,
+        filename_prefix,
+    );
+    let (non_blocking, guard) = tracing_appender::non_blocking(appender);
+
+    let layer: Box<dyn Layer<tracing_subscriber::Registry> + Send + Sync> = Box::new(
+        tracing_subscriber::fmt::layer()
+            .with_ansi(false)
+            .with_writer(non_blocking),
+    );
+    Some((layer, guard))
+}
+
+/// Installs the tracing subscriber: an `EnvFilter` driven by `RUST_LOG`, plus either the
+/// human-readable text layer or a bunyan-style structured JSON layer (one object per event,
+/// with hostname/pid/service name enrichment and span context) depending on `format`, optionally
+/// the tokio-console diagnostics layer, and optionally a rolling file layer alongside stdout.
+/// Returns the file layer's `WorkerGuard`, if any; the caller must keep it alive for the
+/// lifetime of the process.
+fn init_tracing(
+    format: LogFormat,
+    console_enabled: bool,
+    logging: &config::LoggingConfigFile,
+) -> Option<WorkerGuard> {
+    let env_filter = tracing_subscriber::EnvFilter::new(
+        std::env::var("RUST_LOG").unwrap_or_else(|_| "info".into()),
+    );
+
+    let fmt_layer: Box<dyn Layer<tracing_subscriber::Registry> + Send + Sync> = match format {
+        LogFormat::Json => {
+            let service_name =
+                std::env::var("NEARAI_NODE_ID").unwrap_or_else(|_| "nearai-host-api".to_string());
+            Box::new(JsonStorageLayer.and_then(BunyanFormattingLayer::new(
+                service_name,
+                std::io::stdout,
+            )))
+        }
+        LogFormat::Text => Box::new(tracing_subscriber::fmt::layer()),
+    };
+
+    let (file_layer, guard) = match file_log_layer(logging) {
+        Some((layer, guard)) => (Some(layer), Some(guard)),
+        None => (None, None),
+    };
+
+    // The stdout and file layers share one per-layer `EnvFilter`, so it does not gate the
+    // console layer.
+    let mut log_layers = vec![fmt_layer];
+    log_layers.extend(file_layer);
 
-#[tokio::main]
-async fn main() {
-    // Initialize tracing
     tracing_subscriber::registry()
-        .with(tracing_subscriber::EnvFilter::new(
-            std::env::var("RUST_LOG").unwrap_or_else(|_| "info".into()),
-        ))
-        .with(tracing_subscriber::fmt::layer())
+        .with(log_layers.with_filter(env_filter))
+        .with(console_layer(console_enabled))
         .init();
 
-    // Example configuration - replace with actual config loading logic
-    let config = Arc::new(ServerConfig {
-        kp_address: "localhost".to_string(),
-        kp_port: 8080,
-        vm_dir: PathBuf::from("/tmp"),
+    guard
+}
+
+#[tokio::main]
+async fn main() {
+    let cli = Cli::parse();
+    let command = cli.command.unwrap_or(Commands::Serve {
+        config: PathBuf::from("nearai-host.toml"),
     });
 
-    // Start the server
-    if let Err(e) = start_server(config).await {
-        eprintln!("Error starting server: {}", e);
-        std::process::exit(1);
+    let config_path = match &command {
+        Commands::Serve { config } | Commands::Init { config } => config.clone(),
+    };
+    let _log_guard = init_tracing(
+        LogFormat::resolve(&config_path),
+        tokio_console_enabled(&config_path),
+        &config::peek_logging_config(&config_path),
+    );
+
+    match command {
+        Commands::Init { config: config_path } => {
+            if let Err(e) = config::init_config_file(&config_path) {
+                eprintln!("Error writing config: {}", e);
+                std::process::exit(1);
+            }
+            println!("Wrote default config to {}", config_path.display());
+        }
+        Commands::Serve { config: config_path } => {
+            let config = match config::load_config(&config_path) {
+                Ok(config) => config,
+                Err(e) => {
+                    eprintln!("Error loading config: {}", e);
+                    std::process::exit(1);
+                }
+            };
+            let shutdown_timeout = config::peek_shutdown_config(&config_path).drain_timeout();
+            let shutdown = ShutdownHandle::new();
+
+            let started_at = Instant::now();
+            if let Err(e) = start_server(config, shutdown, shutdown_timeout).await {
+                eprintln!("Error starting server: {}", e);
+                std::process::exit(1);
+            }
+            tracing::info!(
+                uptime_secs = started_at.elapsed().as_secs(),
+                "nearai-host-api exiting"
+            );
+        }
     }
 }
@@ -1,18 +1,24 @@
 use anyhow::{anyhow, Context, Result};
 use ini::configparser::ini::Ini;
 use log::{info, warn};
+use regex::Regex;
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 use std::fs::{self, File};
-use std::io::{BufReader, BufWriter, Read};
+use std::io::{BufReader, BufWriter, Read, Write};
 use std::num::ParseIntError;
 use std::path::{Path, PathBuf};
-use std::process::{Command, Stdio};
-use std::sync::{Arc, Mutex};
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::process::{Child, Command, Stdio};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use tracing;
 use uuid::Uuid;
 
+pub mod qmp;
+
+pub use qmp::QmpConn;
+
 /// Merge two JSON values in a nested/dict-like way, similar to Python's merge2.
 fn merge2(a: &Value, b: &Value) -> Value {
     match (a, b) {
@@ -161,13 +167,40 @@ pub struct PortMap {
     pub to_port: u16,
 }
 
+/// A single PCI function to pass through to the guest via `vfio-pci`, selected either by
+/// exact host BDF address or by `vendor`/`device` ID (picking the `index`'th match, so
+/// multiple identical cards can be disambiguated).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VfioDevice {
+    /// Host PCI BDF, e.g. `"65:00.0"`. Takes priority over `vendor`/`device` if both are set.
+    #[serde(default)]
+    pub bdf: Option<String>,
+    /// Host PCI vendor ID in hex, e.g. `"10de"`.
+    #[serde(default)]
+    pub vendor: Option<String>,
+    /// Host PCI device ID in hex, e.g. `"2236"`.
+    #[serde(default)]
+    pub device: Option<String>,
+    /// When resolving by `vendor`/`device`, which match (in sorted BDF order) to use.
+    #[serde(default)]
+    pub index: usize,
+    /// True for the function that should be exposed as the guest's display device; toggles
+    /// `x-vga=on` on the emitted `vfio-pci` device.
+    #[serde(default)]
+    pub is_graphics: bool,
+    /// Explicit guest-side PCI address (e.g. `"04.0"`) to pin this function to, instead of
+    /// letting QEMU assign the next free slot on the passthrough bus.
+    #[serde(default)]
+    pub addr: Option<String>,
+}
+
 /// VMConfig, same as in Python.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct VMConfig {
     pub id: String,
     pub name: String,
     pub vcpu: u32,
-    pub gpu: Vec<String>,
+    pub gpu: Vec<VfioDevice>,
     pub memory: u64,
     pub disk_size: u64,
     pub image: String,
@@ -238,11 +271,318 @@ fn parse_port_mapping(port_str: &str) -> Result<PortMap> {
     }
 }
 
+/// Parse a `key=value,key=value` GPU passthrough spec into a [`VfioDevice`], e.g.
+/// `"bdf=65:00.0,graphics=true"` or `"vendor=10de,device=2236,index=1,addr=04.0"`.
+pub fn parse_vfio_device(spec: &str) -> Result<VfioDevice> {
+    let mut dev = VfioDevice {
+        bdf: None,
+        vendor: None,
+        device: None,
+        index: 0,
+        is_graphics: false,
+        addr: None,
+    };
+    for field in spec.split(',') {
+        let (key, value) = field
+            .split_once('=')
+            .ok_or_else(|| anyhow!("Invalid GPU spec field '{}' in '{}', expected key=value", field, spec))?;
+        match key {
+            "bdf" => dev.bdf = Some(value.to_string()),
+            "vendor" => dev.vendor = Some(value.to_string()),
+            "device" => dev.device = Some(value.to_string()),
+            "index" => dev.index = value.parse().with_context(|| format!("Invalid index '{}'", value))?,
+            "graphics" => dev.is_graphics = value.parse().with_context(|| format!("Invalid graphics flag '{}'", value))?,
+            "addr" => dev.addr = Some(value.to_string()),
+            _ => return Err(anyhow!("Unknown GPU spec key '{}' in '{}'", key, spec)),
+        }
+    }
+    if dev.bdf.is_none() && (dev.vendor.is_none() || dev.device.is_none()) {
+        return Err(anyhow!(
+            "GPU spec '{}' needs either 'bdf' or both 'vendor' and 'device'",
+            spec
+        ));
+    }
+    Ok(dev)
+}
+
+/// Validates that `bdf` has the exact `XX:XX.X` shape (two hex digits, `:`, two hex digits,
+/// `.`, one hex digit) expected by the `/sys/bus/pci/devices/0000:{bdf}/...` paths it's spliced
+/// into. Rejects anything else -- in particular path separators or `..` components that could
+/// otherwise redirect those writes to an attacker-chosen sysfs path.
+fn validate_bdf(bdf: &str) -> Result<()> {
+    let valid = bdf
+        .as_bytes()
+        .iter()
+        .enumerate()
+        .all(|(i, &b)| match i {
+            0 | 1 | 3 | 4 | 6 => b.is_ascii_hexdigit(),
+            2 => b == b':',
+            5 => b == b'.',
+            _ => false,
+        })
+        && bdf.len() == 7;
+    if !valid {
+        return Err(anyhow!(
+            "Invalid PCI BDF '{}': expected the form \"XX:XX.X\" (e.g. \"65:00.0\")",
+            bdf
+        ));
+    }
+    Ok(())
+}
+
+/// Resolves a [`VfioDevice`] to a concrete host PCI BDF (e.g. `"65:00.0"`), either by
+/// returning its `bdf` directly or by scanning `/sys/bus/pci/devices` for the `index`'th
+/// device matching `vendor`/`device`. Validates the resolved BDF's shape either way, since it
+/// gets spliced unescaped into `/sys/bus/pci/...` paths.
+fn resolve_vfio_device(dev: &VfioDevice) -> Result<String> {
+    if let Some(bdf) = &dev.bdf {
+        validate_bdf(bdf)?;
+        return Ok(bdf.clone());
+    }
+    let vendor = dev
+        .vendor
+        .as_deref()
+        .ok_or_else(|| anyhow!("VfioDevice has neither `bdf` nor `vendor`/`device`"))?;
+    let device = dev
+        .device
+        .as_deref()
+        .ok_or_else(|| anyhow!("VfioDevice with `vendor` must also set `device`"))?;
+    let want_vendor = format!("0x{}", vendor.trim_start_matches("0x").to_lowercase());
+    let want_device = format!("0x{}", device.trim_start_matches("0x").to_lowercase());
+
+    let read_id = |dir: &Path, name: &str| -> Option<String> {
+        fs::read_to_string(dir.join(name)).ok().map(|s| s.trim().to_lowercase())
+    };
+
+    let mut matches = Vec::new();
+    for entry in fs::read_dir("/sys/bus/pci/devices").context("Failed to scan /sys/bus/pci/devices")? {
+        let path = entry?.path();
+        if read_id(&path, "vendor").as_deref() == Some(want_vendor.as_str())
+            && read_id(&path, "device").as_deref() == Some(want_device.as_str())
+        {
+            let bdf = path
+                .file_name()
+                .unwrap()
+                .to_string_lossy()
+                .trim_start_matches("0000:")
+                .to_string();
+            matches.push(bdf);
+        }
+    }
+    matches.sort();
+    let bdf = matches.into_iter().nth(dev.index).ok_or_else(|| {
+        anyhow!(
+            "No PCI device found for vendor={} device={} index={}",
+            vendor,
+            device,
+            dev.index
+        )
+    })?;
+    validate_bdf(&bdf)?;
+    Ok(bdf)
+}
+
+/// Drivers we refuse to auto-unbind a GPU from unless the caller explicitly opts in, since
+/// doing so can yank a device out from under the host's own display.
+const AUTO_UNBIND_BLACKLIST: &[&str] = &["nvidia", "amdgpu"];
+
+/// Returns the name of the driver currently bound to `bdf` (the target of the
+/// `/sys/bus/pci/devices/0000:<bdf>/driver` symlink), or `None` if nothing is bound.
+fn current_driver(bdf: &str) -> Option<String> {
+    let link = PathBuf::from(format!("/sys/bus/pci/devices/0000:{}/driver", bdf));
+    fs::read_link(&link)
+        .ok()?
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+}
+
+/// Unbinds `bdf` from `driver` by writing its id to the driver's `unbind` file.
+fn unbind_driver(bdf: &str, driver: &str) -> Result<()> {
+    let unbind_path = format!("/sys/bus/pci/drivers/{}/unbind", driver);
+    fs::write(&unbind_path, format!("0000:{}", bdf))
+        .with_context(|| format!("Failed to unbind {} from driver {}", bdf, driver))
+}
+
+/// Binds `bdf` to `driver` via `driver_override` + a `drivers_probe` kick, then polls until
+/// the bind takes effect or `timeout` elapses.
+fn bind_driver(bdf: &str, driver: &str, timeout: Duration) -> Result<()> {
+    let override_path = format!("/sys/bus/pci/devices/0000:{}/driver_override", bdf);
+    fs::write(&override_path, driver)
+        .with_context(|| format!("Failed to set driver_override={} for {}", driver, bdf))?;
+    fs::write("/sys/bus/pci/drivers_probe", format!("0000:{}", bdf))
+        .with_context(|| format!("Failed to probe drivers for {}", bdf))?;
+
+    let deadline = Instant::now() + timeout;
+    loop {
+        if current_driver(bdf).as_deref() == Some(driver) {
+            return Ok(());
+        }
+        if Instant::now() >= deadline {
+            return Err(anyhow!(
+                "Timed out waiting for {} to bind to driver {}",
+                bdf,
+                driver
+            ));
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+}
+
+/// Parses a Linux `cpulist` string (e.g. `"0-3,8"`, as found in `/sys/devices/system/node/nodeN/cpulist`)
+/// into individual CPU ids.
+fn parse_cpu_list(spec: &str) -> Result<Vec<usize>> {
+    let mut cpus = Vec::new();
+    for part in spec.trim().split(',') {
+        if part.is_empty() {
+            continue;
+        }
+        if let Some((start, end)) = part.split_once('-') {
+            let start: usize = start
+                .parse()
+                .with_context(|| format!("Invalid CPU range '{}'", part))?;
+            let end: usize = end
+                .parse()
+                .with_context(|| format!("Invalid CPU range '{}'", part))?;
+            cpus.extend(start..=end);
+        } else {
+            cpus.push(
+                part.parse()
+                    .with_context(|| format!("Invalid CPU id '{}'", part))?,
+            );
+        }
+    }
+    Ok(cpus)
+}
+
+/// Sets a thread's CPU affinity via `sched_setaffinity`.
+fn set_thread_affinity(tid: i32, cpus: &[usize]) -> Result<()> {
+    unsafe {
+        let mut set: libc::cpu_set_t = std::mem::zeroed();
+        libc::CPU_ZERO(&mut set);
+        for &cpu in cpus {
+            libc::CPU_SET(cpu, &mut set);
+        }
+        let ret = libc::sched_setaffinity(tid, std::mem::size_of::<libc::cpu_set_t>(), &set);
+        if ret != 0 {
+            return Err(anyhow!(
+                "sched_setaffinity({}) failed: {}",
+                tid,
+                std::io::Error::last_os_error()
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Pins each vCPU's host thread (from QMP `query-cpus-fast`'s `thread-id`) to one CPU in
+/// `node_cpus`, round-robin if there are more vCPUs than CPUs, then pins whatever other
+/// threads the QEMU process has (emulator/IO threads) across the rest of `node_cpus` (all of
+/// it when every CPU there already hosts a vCPU). Gives
+/// deterministic NUMA locality for GPU-passthrough workloads instead of a blanket
+/// whole-process `taskset`.
+fn pin_vcpu_node_cpus(qmp: &mut QmpConn, child_pid: u32, node_cpus: &[usize]) -> Result<()> {
+    if node_cpus.is_empty() {
+        return Ok(());
+    }
+
+    let cpus = qmp.execute("query-cpus-fast", None)?;
+    let cpu_list = cpus
+        .as_array()
+        .ok_or_else(|| anyhow!("query-cpus-fast did not return an array"))?;
+
+    let mut vcpu_tids = std::collections::HashSet::new();
+    let mut vcpu_cpus = std::collections::HashSet::new();
+    for (i, cpu) in cpu_list.iter().enumerate() {
+        let tid = cpu
+            .get("thread-id")
+            .and_then(|v| v.as_u64())
+            .ok_or_else(|| anyhow!("query-cpus-fast entry missing thread-id"))? as i32;
+        vcpu_tids.insert(tid);
+        let target_cpu = node_cpus[i % node_cpus.len()];
+        vcpu_cpus.insert(target_cpu);
+        set_thread_affinity(tid, &[target_cpu])
+            .with_context(|| format!("Failed to pin vCPU {} (tid {}) to CPU {}", i, tid, target_cpu))?;
+        tracing::info!("Pinned vCPU {} (tid {}) to host CPU {}", i, tid, target_cpu);
+    }
+
+    // Spread whatever's left (emulator/IO threads) across the node CPUs no vCPU was pinned to,
+    // or across the whole node when every CPU already hosts a vCPU.
+    let rest: Vec<usize> = node_cpus
+        .iter()
+        .copied()
+        .filter(|c| !vcpu_cpus.contains(c))
+        .collect();
+    let rest = if rest.is_empty() { node_cpus } else { rest.as_slice() };
+    let task_dir = format!("/proc/{}/task", child_pid);
+    if let Ok(entries) = fs::read_dir(&task_dir) {
+        for entry in entries.flatten() {
+            let tid: i32 = match entry.file_name().to_string_lossy().parse() {
+                Ok(t) => t,
+                Err(_) => continue,
+            };
+            if vcpu_tids.contains(&tid) {
+                continue;
+            }
+            if let Err(e) = set_thread_affinity(tid, rest) {
+                tracing::warn!("Failed to pin emulator thread {} to node CPUs: {}", tid, e);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// A launched QEMU instance: the child process plus, once the control socket comes up, a
+/// connected QMP session for querying/pausing/resuming/powering it down without killing it.
+/// `stdout_buf`/`stderr_buf` mirror what's written to `qemu_stdout.log`/`qemu_stderr.log` so
+/// callers can inspect boot output live (see [`DStackManager::wait_for_boot`]) instead of only
+/// being able to read it back from disk.
+struct QemuInstance {
+    child: Child,
+    qmp: Option<QmpConn>,
+    vm_dir: PathBuf,
+    stdout_buf: Arc<Mutex<Vec<u8>>>,
+    #[allow(dead_code)]
+    stderr_buf: Arc<Mutex<Vec<u8>>>,
+    /// The writable qcow2 disk (`-drive ...,id=virtio-disk0`) that internal snapshots target.
+    vda_path: PathBuf,
+}
+
+/// The block device id `snapshot`/`restore_running` target — the writable qcow2 disk attached
+/// as `-drive file=<vda_path>,if=none,id=virtio-disk0`.
+const SNAPSHOT_DEVICE_ID: &str = "virtio-disk0";
+
+/// Spawns a thread that copies `reader`'s bytes into `log_file` while also appending them to
+/// `buf`, so callers can inspect a QEMU instance's live output without losing the on-disk log.
+fn spawn_log_tee<R: Read + Send + 'static>(mut reader: R, mut log_file: File, buf: Arc<Mutex<Vec<u8>>>) {
+    thread::spawn(move || {
+        let mut chunk = [0u8; 4096];
+        loop {
+            match reader.read(&mut chunk) {
+                Ok(0) => break,
+                Ok(n) => {
+                    if let Err(e) = log_file.write_all(&chunk[..n]) {
+                        tracing::warn!("Failed to write QEMU log output: {}", e);
+                    }
+                    buf.lock().unwrap().extend_from_slice(&chunk[..n]);
+                }
+                Err(e) => {
+                    tracing::warn!("Failed to read QEMU output stream: {}", e);
+                    break;
+                }
+            }
+        }
+    });
+}
+
 /// The main struct replicating the Python `DStackManager`.
 pub struct DStackManager {
     run_path: PathBuf,
     pub config: DStackConfig,
-    qemu_processes: Arc<Mutex<Vec<std::process::Child>>>,
+    qemu_processes: Arc<Mutex<Vec<QemuInstance>>>,
+    /// Driver each GPU was bound to before we unbound it for passthrough (BDF -> driver name),
+    /// so `shutdown_instances` can rebind it back for the host; it always does so.
+    vfio_original_drivers: Arc<Mutex<std::collections::HashMap<String, String>>>,
 }
 
 impl DStackManager {
@@ -259,7 +599,48 @@ impl DStackManager {
             run_path,
             config,
             qemu_processes: Arc::new(Mutex::new(Vec::new())),
+            vfio_original_drivers: Arc::new(Mutex::new(std::collections::HashMap::new())),
+        }
+    }
+
+    /// Ensures `bdf` is bound to `vfio-pci`, unbinding it from its current driver first if
+    /// needed. Refuses to unbind from a driver in [`AUTO_UNBIND_BLACKLIST`] (e.g. `nvidia`,
+    /// `amdgpu`) unless `allow_unbind_blacklisted` is set, since that can yank a GPU out from
+    /// under the host's own display. The original driver, if any, is recorded so it can be
+    /// restored later via [`Self::rebind_original_drivers`].
+    fn prepare_vfio_device(&self, bdf: &str, allow_unbind_blacklisted: bool) -> Result<()> {
+        match current_driver(bdf) {
+            Some(driver) if driver == "vfio-pci" => Ok(()),
+            Some(driver) => {
+                if AUTO_UNBIND_BLACKLIST.contains(&driver.as_str()) && !allow_unbind_blacklisted {
+                    return Err(anyhow!(
+                        "Refusing to auto-unbind {} from blacklisted driver '{}' without explicit opt-in",
+                        bdf,
+                        driver
+                    ));
+                }
+                tracing::info!("Unbinding {} from driver '{}' for passthrough", bdf, driver);
+                unbind_driver(bdf, &driver)?;
+                self.vfio_original_drivers
+                    .lock()
+                    .unwrap()
+                    .insert(bdf.to_string(), driver);
+                bind_driver(bdf, "vfio-pci", Duration::from_secs(5))
+            }
+            None => bind_driver(bdf, "vfio-pci", Duration::from_secs(5)),
+        }
+    }
+
+    /// Rebinds every GPU unbound by [`Self::prepare_vfio_device`] back to its original driver.
+    fn rebind_original_drivers(&self) -> Result<()> {
+        let mut original = self.vfio_original_drivers.lock().unwrap();
+        for (bdf, driver) in original.drain() {
+            tracing::info!("Rebinding {} back to driver '{}'", bdf, driver);
+            if let Err(e) = bind_driver(&bdf, &driver, Duration::from_secs(5)) {
+                tracing::warn!("Failed to rebind {} to driver '{}': {}", bdf, driver, e);
+            }
         }
+        Ok(())
     }
 
     fn generate_instance_id(&self) -> String {
@@ -325,7 +706,7 @@ impl DStackManager {
     /// - `work_dir_arg`: optional instance directory override. If `None`, we generate a random ID.
     /// - `image_path`: path to the VM image directory (containing `metadata.json`).
     /// - `vcpus`, `memory_str`, `disk_str`: resource specs.
-    /// - `gpus`: a list of GPU device IDs to pass through.
+    /// - `gpus`: PCI functions to pass through via VFIO.
     /// - `ports`: a list of port mappings in `protocol[:address]:from:to` format.
     /// - `local_key_provider`: whether to enable local key provider
     pub fn setup_instance(
@@ -336,7 +717,7 @@ impl DStackManager {
         vcpus: u32,
         memory_str: &str,
         disk_str: &str,
-        gpus: &[String],
+        gpus: &[VfioDevice],
         ports: &[String],
         local_key_provider: bool,
     ) -> Result<()> {
@@ -465,6 +846,7 @@ impl DStackManager {
     /// - `imgdir`: Optional path to the image directory if not specified in manifest.
     /// - `pin_numa`: Whether to pin the VM to the NUMA node of the GPU.
     /// - `hugepage`: Whether to use hugepages for memory.
+    #[allow(clippy::too_many_arguments)]
     pub fn run_instance(
         &self,
         vm_dir: &Path,
@@ -474,6 +856,8 @@ impl DStackManager {
         imgdir: Option<&Path>,
         pin_numa: bool,
         hugepage: bool,
+        allow_unbind_blacklisted: bool,
+        load_snapshot: Option<&str>,
     ) -> Result<()> {
         // Check if QEMU is available
         self.check_qemu_available()?;
@@ -664,14 +1048,20 @@ impl DStackManager {
             final_cmd.push("pcie-root-port,id=pci.1,bus=pcie.0".to_string());
             final_cmd.push("-fw_cfg".to_string());
             final_cmd.push("name=opt/ovmf/X-PciMmio64,string=262144".to_string());
-            for (i, gpu_id) in gpus.iter().enumerate() {
+            for (i, gpu) in gpus.iter().enumerate() {
+                let bdf = resolve_vfio_device(gpu)?;
+                self.prepare_vfio_device(&bdf, allow_unbind_blacklisted)?;
                 final_cmd.push("-object".to_string());
                 final_cmd.push(format!("iommufd,id=iommufd{}", i));
+                let mut device_str = format!("vfio-pci,host={},bus=pci.1,iommufd=iommufd{}", bdf, i);
+                if gpu.is_graphics {
+                    device_str.push_str(",x-vga=on");
+                }
+                if let Some(addr) = &gpu.addr {
+                    device_str.push_str(&format!(",addr={}", addr));
+                }
                 final_cmd.push("-device".to_string());
-                final_cmd.push(format!(
-                    "vfio-pci,host={},bus=pci.1,iommufd=iommufd{}",
-                    gpu_id, i
-                ));
+                final_cmd.push(device_str);
             }
         }
 
@@ -679,9 +1069,25 @@ impl DStackManager {
         final_cmd.push("-append".to_string());
         final_cmd.push(cmdline.to_string());
 
-        // If pin_numa and exactly one GPU, do the same sysfs-based approach as Python
+        // Expose a QMP control socket so we can query/pause/resume/power down the instance
+        // after launch instead of only being able to kill the whole process.
+        let qmp_sock = vm_dir.join("qmp.sock");
+        final_cmd.push("-qmp".to_string());
+        final_cmd.push(format!("unix:{},server=on,wait=off", qmp_sock.display()));
+
+        if let Some(name) = load_snapshot {
+            final_cmd.push("-loadvm".to_string());
+            final_cmd.push(name.to_string());
+        }
+
+        // If pin_numa and exactly one GPU, find the GPU's NUMA node and CPU list. Rather than
+        // a blanket `taskset -c` over the whole QEMU process (which pins emulator/IO threads
+        // to the same set as vCPUs and gives no per-vCPU control), we pin precisely after
+        // launch via `pin_vcpu_node_cpus` below, once QMP can report each vCPU's thread-id.
+        let mut pin_node_cpus: Option<Vec<usize>> = None;
         if pin_numa && gpus.len() == 1 {
-            let sys_path = format!("/sys/bus/pci/devices/0000:{}/numa_node", gpus[0]);
+            let bdf = resolve_vfio_device(&gpus[0])?;
+            let sys_path = format!("/sys/bus/pci/devices/0000:{}/numa_node", bdf);
             let numa_node = fs::read_to_string(&sys_path)
                 .with_context(|| format!("Failed to read NUMA node from {}", sys_path))?
                 .trim()
@@ -693,10 +1099,7 @@ impl DStackManager {
                 .trim()
                 .to_string();
 
-            // Prepend "taskset -c <cpus_list>" to final_cmd
-            let mut pinned = vec!["taskset".to_string(), "-c".to_string(), cpus_list.clone()];
-            pinned.append(&mut final_cmd);
-            final_cmd = pinned;
+            pin_node_cpus = Some(parse_cpu_list(&cpus_list)?);
 
             if hugepage {
                 // We also add:
@@ -789,52 +1192,308 @@ impl DStackManager {
             format!("Failed to create stderr log file: {}", stderr_log.display())
         })?;
 
-        // 6) spawn QEMU
-        let child = Command::new(&final_cmd[0])
+        // 6) spawn QEMU, piping stdout/stderr so we can tee them into both the log files and
+        // an in-memory buffer `wait_for_boot` can scan for a readiness pattern.
+        let mut child = Command::new(&final_cmd[0])
             .args(&final_cmd[1..])
             .stdin(Stdio::null())
-            .stdout(Stdio::from(stdout_file))
-            .stderr(Stdio::from(stderr_file))
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
             .spawn()
             .with_context(|| format!("Failed to launch QEMU: {:?}", final_cmd))?;
 
-        tracing::info!("QEMU process started with PID: {}", child.id());
+        let child_pid = child.id();
+        tracing::info!("QEMU process started with PID: {}", child_pid);
+
+        let stdout_buf = Arc::new(Mutex::new(Vec::new()));
+        let stderr_buf = Arc::new(Mutex::new(Vec::new()));
+        if let Some(stdout) = child.stdout.take() {
+            spawn_log_tee(stdout, stdout_file, stdout_buf.clone());
+        }
+        if let Some(stderr) = child.stderr.take() {
+            spawn_log_tee(stderr, stderr_file, stderr_buf.clone());
+        }
+
+        // The socket isn't guaranteed to be listening the instant the process spawns, so
+        // retry the connection briefly before giving up on the control channel.
+        let mut qmp = None;
+        for attempt in 0..20 {
+            match QmpConn::connect(&qmp_sock) {
+                Ok(conn) => {
+                    qmp = Some(conn);
+                    break;
+                }
+                Err(e) => {
+                    if attempt == 19 {
+                        tracing::warn!(
+                            "Failed to connect to QMP socket {} after launch: {}",
+                            qmp_sock.display(),
+                            e
+                        );
+                    } else {
+                        thread::sleep(Duration::from_millis(100));
+                    }
+                }
+            }
+        }
+
+        if let (Some(node_cpus), Some(qmp_conn)) = (pin_node_cpus.as_ref(), qmp.as_mut()) {
+            if let Err(e) = pin_vcpu_node_cpus(qmp_conn, child_pid, node_cpus) {
+                tracing::warn!("Failed to pin vCPU threads to NUMA node CPUs: {}", e);
+            }
+        }
 
         {
             let mut procs = self.qemu_processes.lock().unwrap();
-            procs.push(child);
+            procs.push(QemuInstance {
+                child,
+                qmp,
+                vm_dir: vm_dir.to_path_buf(),
+                stdout_buf,
+                stderr_buf,
+                vda_path: vda,
+            });
         }
         Ok(())
     }
 
+    /// Finds the tracked instance whose `vm_dir` matches, reconnecting its QMP session first if
+    /// we don't already have one, and returns the locked instance list; callers look the
+    /// instance up again in the returned guard.
+    fn qmp_for(&self, vm_dir: &Path) -> Result<MutexGuard<'_, Vec<QemuInstance>>> {
+        let mut procs = self.qemu_processes.lock().unwrap();
+        let instance = procs
+            .iter_mut()
+            .find(|i| i.vm_dir == vm_dir)
+            .ok_or_else(|| anyhow!("No tracked QEMU instance for {}", vm_dir.display()))?;
+        if instance.qmp.is_none() {
+            instance.qmp = Some(QmpConn::connect(&instance.vm_dir.join("qmp.sock"))?);
+        }
+        Ok(procs)
+    }
+
+    /// Returns the instance's `query-status` result (e.g. `running`, `paused`, `shutdown`).
+    pub fn query_status(&self, vm_dir: &Path) -> Result<Value> {
+        let mut procs = self.qmp_for(vm_dir)?;
+        let instance = procs.iter_mut().find(|i| i.vm_dir == vm_dir).unwrap();
+        instance.qmp.as_mut().unwrap().query_status()
+    }
+
+    /// Pauses the instance's guest via QMP `stop`.
+    pub fn pause(&self, vm_dir: &Path) -> Result<()> {
+        let mut procs = self.qmp_for(vm_dir)?;
+        let instance = procs.iter_mut().find(|i| i.vm_dir == vm_dir).unwrap();
+        instance.qmp.as_mut().unwrap().pause()
+    }
+
+    /// Resumes a previously paused instance via QMP `cont`.
+    pub fn resume(&self, vm_dir: &Path) -> Result<()> {
+        let mut procs = self.qmp_for(vm_dir)?;
+        let instance = procs.iter_mut().find(|i| i.vm_dir == vm_dir).unwrap();
+        instance.qmp.as_mut().unwrap().resume()
+    }
+
+    /// Requests a graceful ACPI shutdown of the instance via QMP `system_powerdown`.
+    pub fn system_powerdown(&self, vm_dir: &Path) -> Result<()> {
+        let mut procs = self.qmp_for(vm_dir)?;
+        let instance = procs.iter_mut().find(|i| i.vm_dir == vm_dir).unwrap();
+        instance.qmp.as_mut().unwrap().system_powerdown()
+    }
+
+    /// Checkpoints the instance's disk+RAM state to an internal qcow2 snapshot named `name`,
+    /// via the QMP `snapshot-save` job, blocking until that job's `JOB_STATUS_CHANGE` event
+    /// reports `concluded`. Enables fast warm-start of expensive model-loaded VMs instead of
+    /// cold-booting every time.
+    pub fn snapshot(&self, vm_dir: &Path, name: &str) -> Result<()> {
+        let mut procs = self.qmp_for(vm_dir)?;
+        let instance = procs.iter_mut().find(|i| i.vm_dir == vm_dir).unwrap();
+        tracing::info!(
+            "Snapshotting {} (disk {}) as '{}'",
+            vm_dir.display(),
+            instance.vda_path.display(),
+            name
+        );
+        let qmp = instance.qmp.as_mut().unwrap();
+        let job_id = format!("snapshot-save-{}", name);
+        qmp.execute(
+            "snapshot-save",
+            Some(json!({
+                "job-id": job_id,
+                "tag": name,
+                "vmstate": SNAPSHOT_DEVICE_ID,
+                "devices": [SNAPSHOT_DEVICE_ID],
+            })),
+        )?;
+        qmp.wait_for_event(
+            |event| {
+                let data = event.get("data");
+                data.and_then(|d| d.get("id")).and_then(|v| v.as_str()) == Some(job_id.as_str())
+                    && data.and_then(|d| d.get("status")).and_then(|v| v.as_str())
+                        == Some("concluded")
+            },
+            Duration::from_secs(300),
+        )?;
+        tracing::info!("Snapshot '{}' of {} concluded", name, vm_dir.display());
+        Ok(())
+    }
+
+    /// Resumes a running instance from a previously saved internal snapshot via the QMP
+    /// `snapshot-load` job, blocking until it reports `concluded`. For restoring into a fresh
+    /// process instead, pass `load_snapshot` to [`Self::run_instance`] so QEMU boots straight
+    /// from the snapshot via `-loadvm`.
+    pub fn restore_running(&self, vm_dir: &Path, name: &str) -> Result<()> {
+        let mut procs = self.qmp_for(vm_dir)?;
+        let instance = procs.iter_mut().find(|i| i.vm_dir == vm_dir).unwrap();
+        let qmp = instance.qmp.as_mut().unwrap();
+        let job_id = format!("snapshot-load-{}", name);
+        qmp.execute(
+            "snapshot-load",
+            Some(json!({
+                "job-id": job_id,
+                "tag": name,
+                "vmstate": SNAPSHOT_DEVICE_ID,
+                "devices": [SNAPSHOT_DEVICE_ID],
+            })),
+        )?;
+        qmp.wait_for_event(
+            |event| {
+                let data = event.get("data");
+                data.and_then(|d| d.get("id")).and_then(|v| v.as_str()) == Some(job_id.as_str())
+                    && data.and_then(|d| d.get("status")).and_then(|v| v.as_str())
+                        == Some("concluded")
+            },
+            Duration::from_secs(300),
+        )?;
+        tracing::info!("Restore from snapshot '{}' of {} concluded", name, vm_dir.display());
+        Ok(())
+    }
+
+    /// Blocks until one of `patterns` matches the instance's captured stdout (e.g. a login
+    /// prompt or an app's "listening on" line), so callers can confirm the guest actually
+    /// booted and its service is ready before routing traffic to its forwarded ports, instead
+    /// of sleeping blindly. Returns an error if `timeout` elapses or the QEMU process exits
+    /// first.
+    pub fn wait_for_boot(&self, vm_dir: &Path, patterns: &[Regex], timeout: Duration) -> Result<()> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            {
+                let mut procs = self.qemu_processes.lock().unwrap();
+                let instance = procs
+                    .iter_mut()
+                    .find(|i| i.vm_dir == vm_dir)
+                    .ok_or_else(|| anyhow!("No tracked QEMU instance for {}", vm_dir.display()))?;
+
+                let matched = {
+                    let output = instance.stdout_buf.lock().unwrap();
+                    let text = String::from_utf8_lossy(&output);
+                    patterns.iter().any(|p| p.is_match(&text))
+                };
+                if matched {
+                    return Ok(());
+                }
+
+                if let Ok(Some(status)) = instance.child.try_wait() {
+                    return Err(anyhow!(
+                        "QEMU instance exited with status {:?} before boot pattern matched",
+                        status
+                    ));
+                }
+            }
+
+            if Instant::now() >= deadline {
+                return Err(anyhow!(
+                    "Timed out after {:?} waiting for boot pattern to appear in {} stdout",
+                    timeout,
+                    vm_dir.display()
+                ));
+            }
+            thread::sleep(Duration::from_millis(200));
+        }
+    }
+
     /// Similar to the Python's `shutdown_instances`.
-    /// Terminates each child QEMU process we have started.
+    ///
+    /// Asks each instance to power down gracefully over QMP (ACPI power button) and gives it
+    /// `grace_period` to exit on its own before resorting to `SIGKILL`, which skips any guest
+    /// filesystem flush.
     pub fn shutdown_instances(&self) -> Result<()> {
+        self.shutdown_instances_with_grace_period(Self::DEFAULT_SHUTDOWN_GRACE_PERIOD)
+    }
+
+    /// Default grace period `shutdown_instances` waits for a clean ACPI shutdown before
+    /// falling back to `SIGKILL`.
+    const DEFAULT_SHUTDOWN_GRACE_PERIOD: Duration = Duration::from_secs(30);
+
+    /// Same as [`Self::shutdown_instances`] but with a caller-specified grace period.
+    pub fn shutdown_instances_with_grace_period(&self, grace_period: Duration) -> Result<()> {
         let mut procs = self.qemu_processes.lock().unwrap();
         tracing::info!("Shutting down {} QEMU instances", procs.len());
 
-        for child in procs.iter_mut() {
-            let pid = child.id();
-            tracing::info!("Shutting down QEMU instance (pid {})...", pid);
+        for instance in procs.iter_mut() {
+            let pid = instance.child.id();
+            match instance.qmp.as_mut() {
+                Some(qmp) => match qmp.system_powerdown() {
+                    Ok(_) => tracing::info!("Sent QMP system_powerdown to QEMU process {}", pid),
+                    Err(e) => tracing::warn!(
+                        "Failed to send QMP system_powerdown to QEMU process {}: {}",
+                        pid,
+                        e
+                    ),
+                },
+                None => tracing::warn!(
+                    "No QMP connection for QEMU process {}; skipping graceful shutdown",
+                    pid
+                ),
+            }
+        }
 
-            match child.kill() {
-                Ok(_) => tracing::info!("Sent kill signal to QEMU process {}", pid),
-                Err(e) => {
-                    tracing::warn!("Failed to send kill signal to QEMU process {}: {}", pid, e)
-                }
+        let deadline = Instant::now() + grace_period;
+        loop {
+            let all_exited = procs
+                .iter_mut()
+                .all(|instance| matches!(instance.child.try_wait(), Ok(Some(_))));
+            if all_exited || Instant::now() >= deadline {
+                break;
             }
+            thread::sleep(Duration::from_millis(200));
+        }
 
-            match child.wait() {
-                Ok(status) => {
+        for instance in procs.iter_mut() {
+            let pid = instance.child.id();
+            match instance.child.try_wait() {
+                Ok(Some(status)) => {
                     tracing::info!("QEMU process {} exited with status: {:?}", pid, status)
                 }
-                Err(e) => tracing::error!("Error waiting for QEMU process {}: {:?}", pid, e),
+                Ok(None) => {
+                    tracing::warn!(
+                        "QEMU process {} still alive after {:?} grace period; sending SIGKILL",
+                        pid,
+                        grace_period
+                    );
+                    if let Err(e) = instance.child.kill() {
+                        tracing::warn!("Failed to send kill signal to QEMU process {}: {}", pid, e);
+                    }
+                    match instance.child.wait() {
+                        Ok(status) => tracing::info!(
+                            "QEMU process {} exited with status: {:?}",
+                            pid,
+                            status
+                        ),
+                        Err(e) => {
+                            tracing::error!("Error waiting for QEMU process {}: {:?}", pid, e)
+                        }
+                    }
+                }
+                Err(e) => tracing::error!("Error polling QEMU process {}: {:?}", pid, e),
             }
         }
 
         let count = procs.len();
         procs.clear();
         tracing::info!("Cleared {} QEMU processes from tracking list", count);
+        drop(procs);
+
+        self.rebind_original_drivers()?;
 
         Ok(())
     }
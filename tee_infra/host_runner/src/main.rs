@@ -79,8 +79,9 @@ enum Commands {
         #[arg(short, long, value_delimiter = ',')]
         ports: Vec<String>,
 
-        /// GPU devices to pass through
-        #[arg(short, long, value_delimiter = ',')]
+        /// GPU/VFIO devices to pass through, each a `key=value,...` spec (`bdf=65:00.0` or
+        /// `vendor=10de,device=2236[,index=N][,graphics=true][,addr=04.0]`), separated by ';'
+        #[arg(short, long, value_delimiter = ';')]
         gpus: Vec<String>,
 
         /// Use local key provider instead of remote
@@ -94,6 +95,16 @@ enum Commands {
         /// Enable hugepage support
         #[arg(long, default_value = "false")]
         hugepage: bool,
+
+        /// Allow auto-unbinding a GPU from a blacklisted driver (nvidia, amdgpu) for
+        /// passthrough. Without this, passthrough fails rather than yanking the device out
+        /// from under the host's own display.
+        #[arg(long, default_value = "false")]
+        allow_unbind_blacklisted: bool,
+
+        /// Name of a previously saved internal snapshot to boot straight from via `-loadvm`.
+        #[arg(long)]
+        load_snapshot: Option<String>,
     },
 
     /// List all running instances
@@ -154,6 +165,8 @@ fn main() -> anyhow::Result<()> {
             local_key_provider,
             pin_numa,
             hugepage,
+            allow_unbind_blacklisted,
+            load_snapshot,
         } => {
             // Check if compose file exists and is readable
             check_file(compose_path, "Compose file")?;
@@ -191,6 +204,12 @@ fn main() -> anyhow::Result<()> {
             let (server_addr, _server_handle) = host_api::start_server_in_thread(config)?;
             info!("Host API server started at: {}", server_addr);
 
+            // Parse GPU/VFIO passthrough specs
+            let gpus = gpus
+                .iter()
+                .map(|spec| host_runner::parse_vfio_device(spec))
+                .collect::<anyhow::Result<Vec<_>>>()?;
+
             // Set up the instance
             debug!("Setting up instance");
             manager.setup_instance(
@@ -200,7 +219,7 @@ fn main() -> anyhow::Result<()> {
                 *cpus,
                 memory,
                 disk,
-                gpus,
+                &gpus,
                 ports,
                 *local_key_provider,
             )?;
@@ -218,6 +237,8 @@ fn main() -> anyhow::Result<()> {
                 None,
                 *pin_numa,
                 *hugepage,
+                *allow_unbind_blacklisted,
+                load_snapshot.as_deref(),
             ) {
                 Ok(pid) => {
                     info!("QEMU instance started successfully with PID: {}", pid);
@@ -0,0 +1,135 @@
+//! Minimal QMP (QEMU Machine Protocol) client: a line-delimited JSON stream over the unix
+//! socket QEMU opens when launched with `-qmp unix:<path>,server=on,wait=off`.
+//!
+//! On connect the server sends a `{"QMP": {...}}` greeting. The client must then send
+//! `{"execute": "qmp_capabilities"}` and receive `{"return": {}}` before issuing any other
+//! command. After that, commands are `{"execute": "<cmd>", "arguments": {...}}` and responses
+//! are either `{"return": ...}` or `{"error": {"class", "desc"}}`.
+
+use anyhow::{anyhow, Context, Result};
+use serde_json::{json, Value};
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::UnixStream;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// A connected, capabilities-negotiated QMP session for a single QEMU instance.
+pub struct QmpConn {
+    stream: UnixStream,
+    reader: BufReader<UnixStream>,
+}
+
+impl QmpConn {
+    /// Connects to the QMP unix socket at `path` and performs the capabilities handshake.
+    pub fn connect(path: &Path) -> Result<Self> {
+        let stream = UnixStream::connect(path)
+            .with_context(|| format!("Failed to connect to QMP socket {}", path.display()))?;
+        let reader = BufReader::new(
+            stream
+                .try_clone()
+                .context("Failed to clone QMP socket for reading")?,
+        );
+
+        let mut conn = Self { stream, reader };
+
+        let greeting = conn.read_message()?;
+        if greeting.get("QMP").is_none() {
+            return Err(anyhow!("Unexpected QMP greeting: {}", greeting));
+        }
+
+        conn.execute("qmp_capabilities", None)?;
+        Ok(conn)
+    }
+
+    /// Sends a command and blocks for its reply.
+    pub fn execute(&mut self, command: &str, arguments: Option<Value>) -> Result<Value> {
+        let mut request = json!({ "execute": command });
+        if let Some(args) = arguments {
+            request["arguments"] = args;
+        }
+        let line = serde_json::to_string(&request)? + "\n";
+        self.stream
+            .write_all(line.as_bytes())
+            .with_context(|| format!("Failed to send QMP command '{}'", command))?;
+
+        loop {
+            let message = self.read_message()?;
+            // Asynchronous `{"event": ...}` notifications (SHUTDOWN, RESET, ...) can arrive
+            // interleaved with the reply; skip them rather than mistaking one for our answer.
+            if message.get("event").is_some() {
+                continue;
+            }
+            if let Some(result) = message.get("return") {
+                return Ok(result.clone());
+            }
+            if let Some(error) = message.get("error") {
+                let desc = error
+                    .get("desc")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("unknown QMP error");
+                return Err(anyhow!("QMP command '{}' failed: {}", command, desc));
+            }
+            return Err(anyhow!("Unexpected QMP message: {}", message));
+        }
+    }
+
+    fn read_message(&mut self) -> Result<Value> {
+        let mut line = String::new();
+        let n = self
+            .reader
+            .read_line(&mut line)
+            .context("Failed to read from QMP socket")?;
+        if n == 0 {
+            return Err(anyhow!("QMP socket closed unexpectedly"));
+        }
+        serde_json::from_str(line.trim_end())
+            .with_context(|| format!("Failed to parse QMP message: {}", line.trim_end()))
+    }
+
+    /// Returns the guest's `query-status` result (e.g. `running`, `paused`, `shutdown`).
+    pub fn query_status(&mut self) -> Result<Value> {
+        self.execute("query-status", None)
+    }
+
+    /// Pauses (QMP `stop`) the guest.
+    pub fn pause(&mut self) -> Result<()> {
+        self.execute("stop", None).map(|_| ())
+    }
+
+    /// Resumes (QMP `cont`) a previously paused guest.
+    pub fn resume(&mut self) -> Result<()> {
+        self.execute("cont", None).map(|_| ())
+    }
+
+    /// Requests a graceful ACPI shutdown of the guest.
+    pub fn system_powerdown(&mut self) -> Result<()> {
+        self.execute("system_powerdown", None).map(|_| ())
+    }
+
+    /// Blocks until an asynchronous `{"event": ...}` message matching `predicate` arrives, or
+    /// returns an error once `timeout` elapses. Useful for job-completion notifications like
+    /// `JOB_STATUS_CHANGE`, which don't come back as a command's `return` value.
+    pub fn wait_for_event(
+        &mut self,
+        predicate: impl Fn(&Value) -> bool,
+        timeout: Duration,
+    ) -> Result<Value> {
+        self.stream.set_read_timeout(Some(Duration::from_millis(200)))?;
+        let deadline = Instant::now() + timeout;
+        let result = loop {
+            if Instant::now() >= deadline {
+                break Err(anyhow!("Timed out waiting for QMP event"));
+            }
+            match self.read_message() {
+                Ok(message) => {
+                    if message.get("event").is_some() && predicate(&message) {
+                        break Ok(message);
+                    }
+                }
+                Err(_) => continue,
+            }
+        };
+        self.stream.set_read_timeout(None)?;
+        result
+    }
+}